@@ -1,13 +1,18 @@
 extern crate dbus;
 #[macro_use] extern crate log;
 extern crate env_logger;
+extern crate libc;
 extern crate ncurses;
 extern crate time;
 
 mod ring;
+mod shutdown;
 mod userinterface;
 
+use ring::history::HistoryStore;
 use ring::manager::Manager;
+use ring::memory_daemon::MemoryDaemon;
+use shutdown::Shutdown;
 use userinterface::UserInterface;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -16,16 +21,37 @@ use std::thread;
 fn main() {
     env_logger::init();
 
-    let shared_manager : Arc<Mutex<Manager>> = Arc::new(Mutex::new(Manager::new().ok().expect("Can't initialize ConfigurationManager")));
-    let shared_manager_cloned = shared_manager.clone();
-    let test = thread::spawn(move || {
-        let mut ui = UserInterface::new();
-        ui.draw(shared_manager_cloned);
+    let shutdown = Shutdown::new();
+    shutdown.install_signal_handlers();
+
+    // --offline runs the client against an in-memory daemon
+    let manager = if std::env::args().any(|arg| arg == "--offline") {
+        Manager::with_daemon(Box::new(MemoryDaemon::new()))
+    } else {
+        Manager::new()
+    };
+    let mut manager = manager.expect("Can't initialize ConfigurationManager");
+    match HistoryStore::new() {
+        Some(history) => manager.set_history(history),
+        None => warn!("No data directory found, the history will not be saved"),
+    }
+    let shared_manager : Arc<Mutex<Manager>> = Arc::new(Mutex::new(manager));
+    let listener_manager = shared_manager.clone();
+    let listener_stop = shutdown.flag();
+    let listener = thread::spawn(move || {
+        Manager::handle_signals(listener_manager, listener_stop);
+    });
+    let ui_shutdown = shutdown.clone();
+    let ui = thread::spawn(move || {
+        let mut ui = UserInterface::new(ui_shutdown);
+        ui.draw(shared_manager);
     });
-    Manager::handle_signals(shared_manager);
-    let _ = test.join();
-    // TODO proper quit
+    // The UI quits on ESC, on SIGINT/SIGTERM or when it panics
+    if ui.join().is_err() {
+        error!("User interface crashed");
+    }
+    shutdown.request();
+    let _ = listener.join();
 }
 
-// TODO NAME SERVER
 // TODO LINK TO RORI
@@ -1,27 +1,154 @@
+use ring::api::account_details::AccountDetails;
+use ring::api::device::Device;
 use std::fmt;
 
+/**
+ * Why the daemon failed to register an account
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistrationError {
+    Generic,
+    Auth,
+    Network,
+    Host,
+    ServiceUnavailable,
+    NeedMigration,
+}
+
+/**
+ * Registration of an account, as told by registrationStateChanged
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistrationState {
+    Unregistered,
+    Trying,
+    Registered,
+    Initializing,
+    // error, detail code, detail message
+    Error(RegistrationError, i32, String),
+    Unknown(String),
+}
+
+impl RegistrationState {
+    /**
+     * @param state as sent by the daemon (REGISTERED, ERROR_AUTH...)
+     * @param code detail code of the state
+     * @param detail detail message of the state
+     * @return the registration state
+     */
+    pub fn from_daemon(state: &str, code: i32, detail: &str) -> RegistrationState {
+        let error = match state {
+            "UNREGISTERED" => return RegistrationState::Unregistered,
+            "TRYING" => return RegistrationState::Trying,
+            "REGISTERED" => return RegistrationState::Registered,
+            "INITIALIZING" => return RegistrationState::Initializing,
+            "ERROR_GENERIC" => RegistrationError::Generic,
+            "ERROR_AUTH" => RegistrationError::Auth,
+            "ERROR_NETWORK" => RegistrationError::Network,
+            "ERROR_HOST" => RegistrationError::Host,
+            "ERROR_SERVICE_UNAVAILABLE" => RegistrationError::ServiceUnavailable,
+            "ERROR_NEED_MIGRATION" => RegistrationError::NeedMigration,
+            _ => return RegistrationState::Unknown(state.to_string()),
+        };
+        RegistrationState::Error(error, code, detail.to_string())
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(*self, RegistrationState::Error(..))
+    }
+}
+
+impl fmt::Display for RegistrationState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegistrationState::Unregistered => write!(f, "unregistered"),
+            RegistrationState::Trying => write!(f, "trying"),
+            RegistrationState::Registered => write!(f, "registered"),
+            RegistrationState::Initializing => write!(f, "initializing"),
+            RegistrationState::Error(ref error, code, ref detail) => {
+                let error = match *error {
+                    RegistrationError::Generic => "error",
+                    RegistrationError::Auth => "authentication error",
+                    RegistrationError::Network => "network error",
+                    RegistrationError::Host => "host error",
+                    RegistrationError::ServiceUnavailable => "service unavailable",
+                    RegistrationError::NeedMigration => "needs migration",
+                };
+                if !detail.is_empty() {
+                    write!(f, "{} ({} {})", error, code, detail)
+                } else {
+                    write!(f, "{}", error)
+                }
+            },
+            RegistrationState::Unknown(ref state) => write!(f, "{}", state.to_lowercase()),
+        }
+    }
+}
+
+/**
+ * Where the details of a new account come from
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccountSource {
+    // A new identity, given its alias
+    New,
+    // An archive exported to a file, given its path
+    Archive,
+    // Another device of the account, given the PIN it generated
+    Pin,
+}
+
+impl AccountSource {
+    /**
+     * @param self
+     * @return the source after this one, to cycle through them
+     */
+    pub fn next(&self) -> AccountSource {
+        match *self {
+            AccountSource::New => AccountSource::Archive,
+            AccountSource::Archive => AccountSource::Pin,
+            AccountSource::Pin => AccountSource::New,
+        }
+    }
+}
+
+impl fmt::Display for AccountSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AccountSource::New => write!(f, "create"),
+            AccountSource::Archive => write!(f, "archive"),
+            AccountSource::Pin => write!(f, "PIN"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Account {
     pub id: String,
     pub ring_id: String,
     pub alias: String,
+    // Name registered on the name server, empty if none
+    pub registered_name: String,
     pub enabled: bool,
+    pub registration: RegistrationState,
+    pub details: AccountDetails,
+    // Devices linked to the account, this one first
+    pub devices: Vec<Device>,
 }
 // Used for println!
 impl fmt::Display for Account {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "[{}]: {} ({}) - Active: {}", self.id, self.ring_id, self.alias, self.enabled)
+        write!(f, "[{}]: {} ({}) - Active: {} - {}", self.id, self.ring_id, self.alias, self.enabled, self.registration)
     }
 }
 
 
 impl Account {
-    pub fn null() -> Account {
-        Account {
-            id: String::new(),
-            ring_id: String::new(),
-            alias: String::new(),
-            enabled: false,
-        }
+    /**
+     * @param self
+     * @return if the account registers on a SIP server instead of the DHT
+     */
+    pub fn is_sip(&self) -> bool {
+        self.details.account_type == "SIP"
     }
 }
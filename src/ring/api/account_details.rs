@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// (label, key, kind) of the details shown to users, in display order
+pub static EDITABLE_DETAILS: &[(&str, &str, DetailKind)] = &[
+    ("Alias", "Account.alias", DetailKind::Text),
+    ("Display name", "Account.displayName", DetailKind::Text),
+    ("Enabled", "Account.enable", DetailKind::Bool),
+    ("Hostname", "Account.hostname", DetailKind::Text),
+    ("UPnP", "Account.upnpEnabled", DetailKind::Bool),
+    ("Auto answer", "Account.autoAnswer", DetailKind::Bool),
+    ("Ringtone", "Account.ringtoneEnabled", DetailKind::Bool),
+    ("Ringtone path", "Account.ringtonePath", DetailKind::Text),
+];
+
+// Details only shown for RING accounts, after the EDITABLE_DETAILS
+pub static RING_DETAILS: &[(&str, &str, DetailKind)] = &[
+    ("TURN", "TURN.enable", DetailKind::Bool),
+    ("TURN server", "TURN.server", DetailKind::Text),
+    ("TURN username", "TURN.username", DetailKind::Text),
+    ("TURN password", "TURN.password", DetailKind::Text),
+    ("TURN realm", "TURN.realm", DetailKind::Text),
+    ("STUN", "STUN.enable", DetailKind::Bool),
+    ("STUN server", "STUN.server", DetailKind::Text),
+    ("DHT proxy", "Account.proxyEnabled", DetailKind::Bool),
+    ("DHT proxy server", "Account.proxyServer", DetailKind::Text),
+    ("DHT port", "DHT.port", DetailKind::Port),
+];
+
+// Details only shown for SIP accounts, after the EDITABLE_DETAILS
+pub static SIP_DETAILS: &[(&str, &str, DetailKind)] = &[
+    ("Username", "Account.username", DetailKind::Text),
+    ("Password", "Account.password", DetailKind::Secret),
+    ("TLS", "TLS.enable", DetailKind::Bool),
+    ("TLS port", "TLS.listenerPort", DetailKind::Port),
+    ("TLS CA list", "TLS.certificateListFile", DetailKind::Text),
+    ("TLS certificate", "TLS.certificateFile", DetailKind::Text),
+    ("TLS private key", "TLS.privateKeyFile", DetailKind::Text),
+    ("TLS verify server", "TLS.verifyServer", DetailKind::Bool),
+    ("SRTP", "SRTP.enable", DetailKind::Bool),
+];
+
+/**
+ * @param account_type RING or SIP
+ * @return (label, key, kind) of the details to show for this type of account
+ */
+pub fn editable_details(account_type: &str) -> Vec<(&'static str, &'static str, DetailKind)> {
+    let mut details = EDITABLE_DETAILS.to_vec();
+    if account_type == "SIP" {
+        details.extend_from_slice(SIP_DETAILS);
+    } else {
+        details.extend_from_slice(RING_DETAILS);
+    }
+    details
+}
+
+/**
+ * How the daemon encodes a detail
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetailKind {
+    Text,
+    Bool,
+    Port,
+    // Text not to show, like passwords
+    Secret,
+}
+
+/**
+ * Everything getAccountDetails returns for an account, typed.
+ * Details this client doesn't know are kept in `others`, so
+ * setAccountDetails doesn't lose them. SIP and RING only details
+ * are None when the daemon didn't give them, so they aren't sent back.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountDetails {
+    pub account_type: String,
+    pub alias: String,
+    pub display_name: String,
+    pub username: String,
+    pub hostname: String,
+    pub password: Option<String>,
+    pub enabled: bool,
+    pub upnp_enabled: bool,
+    pub turn_enabled: Option<bool>,
+    pub turn_server: Option<String>,
+    pub turn_username: Option<String>,
+    pub turn_password: Option<String>,
+    pub turn_realm: Option<String>,
+    pub stun_enabled: Option<bool>,
+    pub stun_server: Option<String>,
+    pub proxy_enabled: Option<bool>,
+    pub proxy_server: Option<String>,
+    pub dht_port: Option<u16>,
+    pub auto_answer: bool,
+    pub ringtone_enabled: bool,
+    pub ringtone_path: String,
+    pub tls_enabled: Option<bool>,
+    pub tls_listener_port: Option<u16>,
+    // Certificates of the authorities to trust
+    pub tls_ca_list_file: Option<String>,
+    pub tls_certificate_file: Option<String>,
+    pub tls_private_key_file: Option<String>,
+    pub tls_verify_server: Option<bool>,
+    pub srtp_enabled: Option<bool>,
+    // sdes, or empty if SRTP is disabled
+    pub srtp_key_exchange: Option<String>,
+    pub others: HashMap<String, String>,
+}
+
+impl AccountDetails {
+    /**
+     * Parse the details returned by the daemon
+     * @param details key -> value
+     * @return the typed details
+     */
+    pub fn from_map(details: &HashMap<String, String>) -> AccountDetails {
+        let mut others = details.clone();
+        let o = &mut others;
+        let result = AccountDetails {
+            account_type: take(o, "Account.type"),
+            alias: take(o, "Account.alias"),
+            display_name: take(o, "Account.displayName"),
+            username: take(o, "Account.username"),
+            hostname: take(o, "Account.hostname"),
+            password: o.remove("Account.password"),
+            enabled: take(o, "Account.enable") != "false",
+            upnp_enabled: take(o, "Account.upnpEnabled") == "true",
+            turn_enabled: o.remove("TURN.enable").map(|v| v == "true"),
+            turn_server: o.remove("TURN.server"),
+            turn_username: o.remove("TURN.username"),
+            turn_password: o.remove("TURN.password"),
+            turn_realm: o.remove("TURN.realm"),
+            stun_enabled: o.remove("STUN.enable").map(|v| v == "true"),
+            stun_server: o.remove("STUN.server"),
+            proxy_enabled: o.remove("Account.proxyEnabled").map(|v| v == "true"),
+            proxy_server: o.remove("Account.proxyServer"),
+            dht_port: o.remove("DHT.port").map(|v| v.parse().unwrap_or(0)),
+            auto_answer: take(o, "Account.autoAnswer") == "true",
+            ringtone_enabled: take(o, "Account.ringtoneEnabled") == "true",
+            ringtone_path: take(o, "Account.ringtonePath"),
+            tls_enabled: o.remove("TLS.enable").map(|v| v == "true"),
+            tls_listener_port: o.remove("TLS.listenerPort").map(|v| v.parse().unwrap_or(0)),
+            tls_ca_list_file: o.remove("TLS.certificateListFile"),
+            tls_certificate_file: o.remove("TLS.certificateFile"),
+            tls_private_key_file: o.remove("TLS.privateKeyFile"),
+            tls_verify_server: o.remove("TLS.verifyServer").map(|v| v == "true"),
+            srtp_enabled: o.remove("SRTP.enable").map(|v| v == "true"),
+            srtp_key_exchange: o.remove("SRTP.keyExchange"),
+            others: HashMap::new(),
+        };
+        AccountDetails { others, ..result }
+    }
+
+    /**
+     * Encode the details for setAccountDetails
+     * @param self
+     * @return key -> value
+     */
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let mut details = self.others.clone();
+        {
+            let mut put = |key: &str, value: String| { details.insert(key.to_string(), value); };
+            put("Account.type", self.account_type.clone());
+            put("Account.alias", self.alias.clone());
+            put("Account.displayName", self.display_name.clone());
+            put("Account.username", self.username.clone());
+            put("Account.hostname", self.hostname.clone());
+            put("Account.enable", format!("{}", self.enabled));
+            put("Account.upnpEnabled", format!("{}", self.upnp_enabled));
+            put("Account.autoAnswer", format!("{}", self.auto_answer));
+            put("Account.ringtoneEnabled", format!("{}", self.ringtone_enabled));
+            put("Account.ringtonePath", self.ringtone_path.clone());
+        }
+        {
+            let mut put = |key: &str, value: Option<String>| {
+                if let Some(value) = value {
+                    details.insert(key.to_string(), value);
+                }
+            };
+            put("TURN.enable", self.turn_enabled.map(|v| format!("{}", v)));
+            put("TURN.server", self.turn_server.clone());
+            put("TURN.username", self.turn_username.clone());
+            put("TURN.password", self.turn_password.clone());
+            put("TURN.realm", self.turn_realm.clone());
+            put("STUN.enable", self.stun_enabled.map(|v| format!("{}", v)));
+            put("STUN.server", self.stun_server.clone());
+            put("Account.proxyEnabled", self.proxy_enabled.map(|v| format!("{}", v)));
+            put("Account.proxyServer", self.proxy_server.clone());
+            put("DHT.port", self.dht_port.map(|v| format!("{}", v)));
+            put("Account.password", self.password.clone());
+            put("TLS.enable", self.tls_enabled.map(|v| format!("{}", v)));
+            put("TLS.listenerPort", self.tls_listener_port.map(|v| format!("{}", v)));
+            put("TLS.certificateListFile", self.tls_ca_list_file.clone());
+            put("TLS.certificateFile", self.tls_certificate_file.clone());
+            put("TLS.privateKeyFile", self.tls_private_key_file.clone());
+            put("TLS.verifyServer", self.tls_verify_server.map(|v| format!("{}", v)));
+            put("SRTP.enable", self.srtp_enabled.map(|v| format!("{}", v)));
+            put("SRTP.keyExchange", self.srtp_key_exchange.clone());
+        }
+        details
+    }
+
+    /**
+     * @param self
+     * @param key of the detail, as used by the daemon
+     * @return the encoded value of the detail
+     */
+    pub fn get(&self, key: &str) -> String {
+        self.to_map().remove(key).unwrap_or_default()
+    }
+
+    /**
+     * Change a detail by its daemon key
+     * @param self
+     * @param key of the detail
+     * @param value encoded as the daemon does
+     * @return an error if the value doesn't fit the detail
+     */
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let kind = EDITABLE_DETAILS.iter().chain(RING_DETAILS.iter()).chain(SIP_DETAILS.iter())
+            .find(|d| d.1 == key).map(|d| d.2).unwrap_or(DetailKind::Text);
+        match kind {
+            DetailKind::Bool if value != "true" && value != "false" =>
+                return Err(format!("{} must be true or false", key)),
+            DetailKind::Port if value.parse::<u16>().is_err() =>
+                return Err(format!("{} must be a port number", key)),
+            _ => {}
+        }
+        let mut details = self.to_map();
+        details.insert(key.to_string(), value.to_string());
+        if key == "SRTP.enable" {
+            // The daemon doesn't encrypt without a key exchange
+            let key_exchange = if value == "true" {"sdes"} else {""};
+            details.insert(String::from("SRTP.keyExchange"), key_exchange.to_string());
+        }
+        *self = AccountDetails::from_map(&details);
+        Ok(())
+    }
+}
+
+/**
+ * How a SIP account reaches its registrar. The daemon only tells
+ * UDP from TLS: TCP isn't one of its details.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SipTransport {
+    Udp,
+    Tls,
+}
+
+impl SipTransport {
+    /**
+     * @param self
+     * @return the transport after this one, to cycle through them
+     */
+    pub fn next(&self) -> SipTransport {
+        match *self {
+            SipTransport::Udp => SipTransport::Tls,
+            SipTransport::Tls => SipTransport::Udp,
+        }
+    }
+}
+
+impl fmt::Display for SipTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SipTransport::Udp => write!(f, "UDP"),
+            SipTransport::Tls => write!(f, "TLS"),
+        }
+    }
+}
+
+/**
+ * What is asked to create a SIP account, e.g. a line of a PBX
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SipSettings {
+    // username@hostname if empty
+    pub alias: String,
+    pub hostname: String,
+    pub username: String,
+    pub password: String,
+    pub transport: SipTransport,
+    // The following are only used with TLS, empty or 0 for the daemon's default
+    pub tls_listener_port: u16,
+    // Certificates of the authorities to trust
+    pub tls_ca_list_file: String,
+    pub tls_certificate_file: String,
+    pub tls_private_key_file: String,
+    pub tls_verify_server: bool,
+    // Encrypt the media with SRTP (SDES)
+    pub srtp: bool,
+}
+
+impl SipSettings {
+    /**
+     * @param hostname of the registrar
+     * @param username
+     * @param password
+     * @return settings for a UDP account without SRTP
+     */
+    pub fn new(hostname: &str, username: &str, password: &str) -> SipSettings {
+        SipSettings {
+            alias: String::new(),
+            hostname: hostname.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            transport: SipTransport::Udp,
+            tls_listener_port: 0,
+            tls_ca_list_file: String::new(),
+            tls_certificate_file: String::new(),
+            tls_private_key_file: String::new(),
+            tls_verify_server: true,
+            srtp: false,
+        }
+    }
+
+    /**
+     * @param self
+     * @return why the daemon can't register the account, if it can't
+     */
+    pub fn check(&self) -> Result<(), String> {
+        if self.hostname.trim().is_empty() {
+            return Err(String::from("the hostname is missing"));
+        }
+        if self.username.trim().is_empty() {
+            return Err(String::from("the username is missing"));
+        }
+        Ok(())
+    }
+
+    /**
+     * Encode the settings for addAccount
+     * @param self
+     * @return key -> value
+     */
+    pub fn to_map(&self) -> HashMap<String, String> {
+        let (hostname, username) = (self.hostname.trim(), self.username.trim());
+        let alias = if !self.alias.is_empty() {self.alias.clone()} else {format!("{}@{}", username, hostname)};
+        let tls = self.transport == SipTransport::Tls;
+        let mut details = HashMap::new();
+        {
+            let mut put = |key: &str, value: String| { details.insert(key.to_string(), value); };
+            put("Account.type", String::from("SIP"));
+            put("Account.alias", alias);
+            put("Account.hostname", hostname.to_string());
+            put("Account.username", username.to_string());
+            put("Account.password", self.password.clone());
+            put("TLS.enable", format!("{}", tls));
+            if tls {
+                if self.tls_listener_port != 0 {
+                    put("TLS.listenerPort", format!("{}", self.tls_listener_port));
+                }
+                put("TLS.certificateListFile", self.tls_ca_list_file.clone());
+                put("TLS.certificateFile", self.tls_certificate_file.clone());
+                put("TLS.privateKeyFile", self.tls_private_key_file.clone());
+                put("TLS.verifyServer", format!("{}", self.tls_verify_server));
+            }
+            put("SRTP.enable", format!("{}", self.srtp));
+            put("SRTP.keyExchange", String::from(if self.srtp {"sdes"} else {""}));
+        }
+        details
+    }
+}
+
+fn take(details: &mut HashMap<String, String>, key: &str) -> String {
+    details.remove(key).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::api::account_details::{AccountDetails, SipSettings, SipTransport};
+    use std::collections::HashMap;
+
+    fn ring_details() -> HashMap<String, String> {
+        let mut details = HashMap::new();
+        details.insert(String::from("Account.type"), String::from("RING"));
+        details.insert(String::from("Account.alias"), String::from("alice"));
+        details
+    }
+
+    #[test]
+    fn sip_details_are_only_sent_back_when_given() {
+        let mut details = AccountDetails::from_map(&ring_details());
+        assert_eq!(details.tls_listener_port, None);
+        details.set("Account.alias", "bob").unwrap();
+        let map = details.to_map();
+        assert_eq!(map.get("Account.alias").unwrap(), "bob");
+        for key in ["Account.password", "TLS.enable", "TLS.listenerPort", "SRTP.enable", "SRTP.keyExchange"].iter() {
+            assert!(!map.contains_key(*key), "{} added", key);
+        }
+
+        let sip = AccountDetails::from_map(&SipSettings::new("pbx.example.com", "1001", "secret").to_map());
+        assert_eq!(sip.password, Some(String::from("secret")));
+        assert_eq!(sip.tls_enabled, Some(false));
+        // Left to the daemon without TLS
+        assert_eq!(sip.tls_listener_port, None);
+    }
+
+    #[test]
+    fn ring_details_are_only_sent_back_when_given() {
+        let given = SipSettings::new("pbx.example.com", "1001", "secret").to_map();
+        let mut sip = AccountDetails::from_map(&given);
+        assert_eq!(sip.dht_port, None);
+        sip.set("Account.alias", "office").unwrap();
+        let map = sip.to_map();
+        assert_eq!(map.get("Account.alias").unwrap(), "office");
+        for key in ["TURN.enable", "TURN.server", "TURN.username", "TURN.password", "TURN.realm", "STUN.enable",
+                    "STUN.server", "Account.proxyEnabled", "Account.proxyServer", "DHT.port"].iter() {
+            assert!(!map.contains_key(*key), "{} added", key);
+        }
+        // Everything else comes back as given
+        for (key, value) in given.iter().filter(|&(key, _)| key != "Account.alias") {
+            assert_eq!(map.get(key), Some(value), "{} changed", key);
+        }
+
+        let mut details = ring_details();
+        details.insert(String::from("DHT.port"), String::from("4222"));
+        details.insert(String::from("TURN.enable"), String::from("true"));
+        let ring = AccountDetails::from_map(&details);
+        assert_eq!(ring.dht_port, Some(4222));
+        assert_eq!(ring.turn_enabled, Some(true));
+        assert_eq!(ring.to_map().get("DHT.port").unwrap(), "4222");
+    }
+
+    #[test]
+    fn srtp_comes_with_its_key_exchange() {
+        let mut details = AccountDetails::from_map(&SipSettings::new("pbx.example.com", "1001", "").to_map());
+        assert_eq!(details.srtp_key_exchange, Some(String::new()));
+        details.set("SRTP.enable", "true").unwrap();
+        assert_eq!(details.srtp_enabled, Some(true));
+        assert_eq!(details.get("SRTP.keyExchange"), "sdes");
+        details.set("SRTP.enable", "false").unwrap();
+        assert_eq!(details.get("SRTP.keyExchange"), "");
+    }
+
+    #[test]
+    fn tls_settings_are_only_given_with_tls() {
+        let mut settings = SipSettings::new("pbx.example.com", "1001", "");
+        settings.tls_ca_list_file = String::from("/etc/ssl/pbx.pem");
+        assert!(!settings.to_map().contains_key("TLS.certificateListFile"));
+        settings.transport = SipTransport::Tls;
+        settings.tls_listener_port = 5061;
+        let map = settings.to_map();
+        assert_eq!(map.get("Account.hostname").unwrap(), "pbx.example.com");
+        assert_eq!(map.get("TLS.enable").unwrap(), "true");
+        assert_eq!(map.get("TLS.listenerPort").unwrap(), "5061");
+        assert_eq!(map.get("TLS.certificateListFile").unwrap(), "/etc/ssl/pbx.pem");
+        assert_eq!(map.get("TLS.verifyServer").unwrap(), "true");
+    }
+}
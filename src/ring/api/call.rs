@@ -0,0 +1,241 @@
+use ring::api::interaction::Direction;
+use std::collections::HashMap;
+use std::fmt;
+use time::{self, Duration, Timespec, Tm};
+
+/**
+ * State of a call, as told by callStateChanged
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallState {
+    Incoming,
+    Connecting,
+    Ringing,
+    Current,
+    Hold,
+    Hungup,
+    Busy,
+    Failure,
+    Inactive,
+    Over,
+    Unknown(String),
+}
+
+impl CallState {
+    /**
+     * @param state as sent by the daemon (RINGING, CURRENT...)
+     * @return the call state
+     */
+    pub fn from_daemon(state: &str) -> CallState {
+        match state {
+            "INCOMING" => CallState::Incoming,
+            "CONNECTING" => CallState::Connecting,
+            "RINGING" => CallState::Ringing,
+            "CURRENT" | "UNHOLD" => CallState::Current,
+            "HOLD" => CallState::Hold,
+            "HUNGUP" => CallState::Hungup,
+            "BUSY" => CallState::Busy,
+            "FAILURE" => CallState::Failure,
+            "INACTIVE" => CallState::Inactive,
+            "OVER" => CallState::Over,
+            other => CallState::Unknown(other.to_string()),
+        }
+    }
+
+    /**
+     * @param self
+     * @return if the call ended
+     */
+    pub fn is_over(&self) -> bool {
+        matches!(*self, CallState::Hungup | CallState::Busy | CallState::Failure | CallState::Over)
+    }
+}
+
+impl fmt::Display for CallState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = match *self {
+            CallState::Incoming => "incoming",
+            CallState::Connecting => "connecting",
+            CallState::Ringing => "ringing",
+            CallState::Current => "in call",
+            CallState::Hold => "on hold",
+            CallState::Hungup => "hung up",
+            CallState::Busy => "busy",
+            CallState::Failure => "failed",
+            CallState::Inactive => "inactive",
+            CallState::Over => "over",
+            CallState::Unknown(ref state) => state,
+        };
+        write!(f, "{}", state)
+    }
+}
+
+/**
+ * A call known by the daemon
+ */
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub id: String,
+    pub account_id: String,
+    // Who is called, or calling
+    pub peer: String,
+    pub direction: Direction,
+    pub state: CallState,
+    // When the call was placed or received
+    pub created: Tm,
+    // When the call was answered
+    pub start: Option<Tm>,
+    pub muted: bool,
+}
+
+impl Call {
+    pub fn new(id: &str, account_id: &str, peer: &str, direction: Direction, state: CallState) -> Call {
+        Call {
+            id: id.to_string(),
+            account_id: account_id.to_string(),
+            peer: strip_scheme(peer),
+            direction,
+            state,
+            created: time::now(),
+            start: None,
+            muted: false,
+        }
+    }
+
+    /**
+     * Build a call from getCallDetails
+     * @param id of the call
+     * @param details returned by the daemon
+     * @return the call
+     */
+    pub fn from_details(id: &str, details: &HashMap<String, String>) -> Call {
+        let detail = |key: &str| details.get(key).map(|d| d.as_str()).unwrap_or("");
+        let direction = if detail("CALL_TYPE") == "0" {Direction::Incoming} else {Direction::Outgoing};
+        let mut call = Call::new(id, detail("ACCOUNTID"), detail("PEER_NUMBER"), direction,
+                                 CallState::from_daemon(detail("CALL_STATE")));
+        call.muted = detail("AUDIO_MUTED") == "true";
+        call.start = match detail("TIMESTAMP_START").parse::<i64>() {
+            Ok(start) if start > 0 => Some(time::at(Timespec::new(start, 0))),
+            _ => None,
+        };
+        if let Some(start) = call.start {
+            call.created = start;
+        }
+        call
+    }
+
+    /**
+     * Change the state of the call
+     * @param self
+     * @param state
+     */
+    pub fn set_state(&mut self, state: CallState) {
+        if state == CallState::Current && self.start.is_none() {
+            self.start = Some(time::now());
+        }
+        self.state = state;
+    }
+
+    /**
+     * @param self
+     * @return how long the call lasts since it was answered
+     */
+    pub fn duration(&self) -> Option<Duration> {
+        self.start.map(|start| time::now() - start)
+    }
+}
+
+/**
+ * How a call ended, for the call log
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallOutcome {
+    Answered,
+    Missed,
+    Refused,
+    Failed,
+}
+
+impl fmt::Display for CallOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outcome = match *self {
+            CallOutcome::Answered => "answered",
+            CallOutcome::Missed => "missed",
+            CallOutcome::Refused => "refused",
+            CallOutcome::Failed => "failed",
+        };
+        write!(f, "{}", outcome)
+    }
+}
+
+/**
+ * A finished call, as kept in the call log
+ */
+#[derive(Debug, Clone)]
+pub struct CallRecord {
+    pub call_id: String,
+    pub account_id: String,
+    pub peer: String,
+    pub direction: Direction,
+    // When the call was placed or received
+    pub start: Tm,
+    pub duration: Duration,
+    pub outcome: CallOutcome,
+}
+
+impl CallRecord {
+    /**
+     * Record a call which just ended
+     * @param call as tracked until now
+     * @param end the state ending the call
+     * @param refused if we refused the call
+     * @return the record
+     */
+    pub fn from_call(call: &Call, end: &CallState, refused: bool) -> CallRecord {
+        let outcome = if call.start.is_some() {
+            CallOutcome::Answered
+        } else if refused {
+            CallOutcome::Refused
+        } else if *end == CallState::Busy || *end == CallState::Failure {
+            CallOutcome::Failed
+        } else {
+            CallOutcome::Missed
+        };
+        CallRecord {
+            call_id: call.id.clone(),
+            account_id: call.account_id.clone(),
+            peer: call.peer.clone(),
+            direction: call.direction,
+            start: call.created,
+            duration: call.duration().unwrap_or(Duration::zero()),
+            outcome,
+        }
+    }
+}
+
+/**
+ * The daemon gives peers as uris, keep only the id for Ring peers
+ * @param peer e.g. ring:a1b2c3
+ * @return e.g. a1b2c3
+ */
+fn strip_scheme(peer: &str) -> String {
+    peer.strip_prefix("ring:").unwrap_or(peer).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::api::call::{Call, CallOutcome, CallRecord, CallState};
+    use ring::api::interaction::Direction;
+    use time;
+    use time::Duration;
+
+    #[test]
+    fn unanswered_calls_are_logged_when_they_came() {
+        let mut call = Call::new("call1", "account1", "ring:a1b2c3", Direction::Incoming, CallState::Incoming);
+        call.created = time::now() - Duration::minutes(2);
+        let record = CallRecord::from_call(&call, &CallState::Over, false);
+        assert_eq!(record.outcome, CallOutcome::Missed);
+        assert_eq!(record.start, call.created);
+        assert_eq!(record.duration, Duration::zero());
+    }
+}
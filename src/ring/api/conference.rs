@@ -0,0 +1,51 @@
+use std::fmt;
+
+/**
+ * State of a conference, as told by conferenceChanged
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConferenceState {
+    // We talk in the conference
+    Active,
+    // The participants talk without us
+    Detached,
+    Hold,
+    Unknown(String),
+}
+
+impl ConferenceState {
+    /**
+     * @param state as sent by the daemon (ACTIVE_ATTACHED, HOLD...)
+     * @return the conference state
+     */
+    pub fn from_daemon(state: &str) -> ConferenceState {
+        match state {
+            "ACTIVE_ATTACHED" | "ACTIVE_ATTACHED_REC" => ConferenceState::Active,
+            "ACTIVE_DETACHED" | "ACTIVE_DETACHED_REC" => ConferenceState::Detached,
+            "HOLD" | "HOLD_REC" => ConferenceState::Hold,
+            other => ConferenceState::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ConferenceState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConferenceState::Active => write!(f, "active"),
+            ConferenceState::Detached => write!(f, "detached"),
+            ConferenceState::Hold => write!(f, "on hold"),
+            ConferenceState::Unknown(ref state) => write!(f, "{}", state.to_lowercase()),
+        }
+    }
+}
+
+/**
+ * Calls merged together
+ */
+#[derive(Debug, Clone)]
+pub struct Conference {
+    pub id: String,
+    // ids of the calls in the conference
+    pub participants: Vec<String>,
+    pub state: ConferenceState,
+}
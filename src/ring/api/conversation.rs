@@ -0,0 +1,113 @@
+use ring::api::interaction::{Direction, Interaction};
+use time::Tm;
+
+/**
+ * Interactions exchanged between an account and a peer, in both directions
+ */
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    pub account_id: String,
+    pub peer_ring_id: String,
+    // Oldest first
+    pub interactions: Vec<Interaction>,
+    // Interactions before this index were read
+    pub read_marker: usize,
+}
+
+impl Conversation {
+    pub fn new(account_id: &str, peer_ring_id: &str) -> Conversation {
+        Conversation {
+            account_id: account_id.to_string(),
+            peer_ring_id: peer_ring_id.to_string(),
+            interactions: Vec::new(),
+            read_marker: 0,
+        }
+    }
+
+    /**
+     * Add a new interaction. Answering means the conversation was read.
+     * @param self
+     * @param interaction
+     */
+    pub fn push(&mut self, interaction: Interaction) {
+        let outgoing = interaction.direction == Direction::Outgoing;
+        self.interactions.push(interaction);
+        if outgoing {
+            self.mark_read();
+        }
+    }
+
+    /**
+     * Add older interactions, which are considered read
+     * @param self
+     * @param interactions oldest first
+     */
+    pub fn prepend(&mut self, interactions: Vec<Interaction>) {
+        self.read_marker += interactions.len();
+        let mut interactions = interactions;
+        interactions.append(&mut self.interactions);
+        self.interactions = interactions;
+    }
+
+    pub fn mark_read(&mut self) {
+        self.read_marker = self.interactions.len();
+    }
+
+    /**
+     * @param self
+     * @return how many incoming interactions were not read
+     */
+    pub fn unread(&self) -> usize {
+        self.interactions.iter().skip(self.read_marker)
+            .filter(|i| i.direction == Direction::Incoming).count()
+    }
+
+    /**
+     * @param self
+     * @return time of the last interaction, None if empty
+     */
+    pub fn last_activity(&self) -> Option<Tm> {
+        self.interactions.last().map(|i| i.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::api::conversation::Conversation;
+    use ring::api::interaction::{Direction, Interaction, MessageStatus};
+    use std::collections::HashMap;
+    use time;
+
+    fn interaction(direction: Direction) -> Interaction {
+        Interaction {
+            id: 0,
+            author_ring_id: String::from("d4e5f6"),
+            peer_ring_id: String::from("d4e5f6"),
+            payloads: HashMap::new(),
+            time: time::now(),
+            direction,
+            status: MessageStatus::Delivered,
+        }
+    }
+
+    #[test]
+    fn unread_interactions_are_counted_until_read() {
+        let mut conversation = Conversation::new("acc", "d4e5f6");
+        assert!(conversation.last_activity().is_none());
+        conversation.push(interaction(Direction::Incoming));
+        conversation.push(interaction(Direction::Incoming));
+        assert_eq!(conversation.unread(), 2);
+        conversation.mark_read();
+        assert_eq!(conversation.unread(), 0);
+
+        conversation.push(interaction(Direction::Incoming));
+        assert_eq!(conversation.unread(), 1);
+        conversation.push(interaction(Direction::Outgoing));
+        assert_eq!(conversation.unread(), 0);
+
+        conversation.prepend(vec![interaction(Direction::Incoming)]);
+        assert_eq!(conversation.interactions.len(), 5);
+        assert_eq!(conversation.unread(), 0);
+        assert!(conversation.last_activity().is_some());
+    }
+}
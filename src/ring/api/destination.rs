@@ -0,0 +1,133 @@
+use ring::api::name::is_ring_id;
+use ring::error::RingError;
+
+/**
+ * Build the uri to call from what the user typed, for the calling account
+ * @param account_type of the calling account (RING or SIP)
+ * @param input a ring id, a ring:, jami: or sip: uri, user@host or a phone number
+ * @return the uri to give to placeCall
+ */
+pub fn parse_destination(account_type: &str, input: &str) -> Result<String, RingError> {
+    let input = input.trim();
+    let sip_account = account_type == "SIP";
+    if input.is_empty() {
+        return Err(invalid(input, "nothing to call"));
+    }
+    match scheme(input) {
+        Some((scheme, rest)) => match &*scheme {
+            "ring" | "jami" => {
+                if sip_account {
+                    Err(invalid(input, "a SIP account can't call a Ring id"))
+                } else if !is_ring_id(rest) {
+                    Err(invalid(input, "not a Ring id"))
+                } else {
+                    Ok(format!("{}:{}", scheme, rest.to_lowercase()))
+                }
+            },
+            "sip" | "sips" => {
+                if !sip_account {
+                    Err(invalid(input, "a Ring account can't call a SIP uri"))
+                } else if rest.is_empty() || rest.contains(char::is_whitespace) {
+                    Err(invalid(input, "not a SIP uri"))
+                } else {
+                    Ok(format!("{}:{}", scheme, rest))
+                }
+            },
+            _ => Err(invalid(input, "unknown scheme")),
+        },
+        None if sip_account => {
+            if is_phone_number(input) {
+                let number: String = input.chars().filter(|c| !" -.()".contains(*c)).collect();
+                Ok(format!("sip:{}", number))
+            } else if input.contains(char::is_whitespace) {
+                Err(invalid(input, "not a SIP uri"))
+            } else {
+                Ok(format!("sip:{}", input))
+            }
+        },
+        None => {
+            if is_ring_id(input) {
+                Ok(format!("ring:{}", input.to_lowercase()))
+            } else if is_phone_number(input) {
+                Err(invalid(input, "a Ring account can't call a phone number"))
+            } else {
+                Err(invalid(input, "not a Ring id, look the name up first"))
+            }
+        },
+    }
+}
+
+/**
+ * @param input
+ * @return (lowercase scheme, rest) if input starts with a scheme, like sip:
+ */
+fn scheme(input: &str) -> Option<(String, &str)> {
+    let colon = input.find(':')?;
+    let scheme = &input[..colon];
+    if scheme.is_empty() || !scheme.chars().all(|c| c.is_ascii_alphabetic()) {
+        // e.g. user@host:5060
+        return None;
+    }
+    Some((scheme.to_lowercase(), &input[colon + 1..]))
+}
+
+fn is_phone_number(input: &str) -> bool {
+    let input = input.strip_prefix('+').unwrap_or(input);
+    input.chars().any(|c| c.is_ascii_digit()) && input.chars().all(|c| c.is_ascii_digit() || " -.()*#".contains(c))
+}
+
+fn invalid(destination: &str, reason: &str) -> RingError {
+    RingError::InvalidDestination {
+        destination: destination.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::api::destination::parse_destination;
+    use ring::error::RingError;
+
+    const RING_ID: &str = "a1b2c3a1b2c3a1b2c3a1b2c3a1b2c3a1b2c3a1b2";
+
+    #[test]
+    fn ring_destinations() {
+        let ring_uri = format!("ring:{}", RING_ID);
+        assert_eq!(parse_destination("RING", &RING_ID.to_uppercase()), Ok(ring_uri.clone()));
+        assert_eq!(parse_destination("RING", &format!(" {} ", ring_uri)), Ok(ring_uri.clone()));
+        assert_eq!(parse_destination("RING", &format!("jami:{}", RING_ID)), Ok(format!("jami:{}", RING_ID)));
+        assert!(parse_destination("RING", "bob").is_err());
+        assert!(parse_destination("RING", "+33 1 23 45 67 89").is_err());
+        assert!(parse_destination("RING", "sip:bob@example.com").is_err());
+        assert!(parse_destination("RING", "ring:bob").is_err());
+    }
+
+    #[test]
+    fn short_hex_strings_are_not_ring_ids() {
+        for input in &["face", "bad", "ring:a1b2c3", "jami:a1b2c3"] {
+            assert_eq!(parse_destination("RING", input), Err(RingError::InvalidDestination {
+                destination: input.to_string(),
+                reason: String::from(if input.contains(':') { "not a Ring id" } else { "not a Ring id, look the name up first" }),
+            }));
+        }
+        assert_eq!(parse_destination("RING", "1234"), Err(RingError::InvalidDestination {
+            destination: String::from("1234"),
+            reason: String::from("a Ring account can't call a phone number"),
+        }));
+    }
+
+    #[test]
+    fn sip_destinations() {
+        assert_eq!(parse_destination("SIP", "sip:bob@example.com"), Ok(String::from("sip:bob@example.com")));
+        assert_eq!(parse_destination("SIP", "bob@example.com:5060"), Ok(String::from("sip:bob@example.com:5060")));
+        assert_eq!(parse_destination("SIP", "+33 (1) 23-45-67-89"), Ok(String::from("sip:+33123456789")));
+        assert_eq!(parse_destination("SIP", "1234"), Ok(String::from("sip:1234")));
+        let ring_uri = format!("ring:{}", RING_ID);
+        assert_eq!(parse_destination("SIP", &ring_uri), Err(RingError::InvalidDestination {
+            destination: ring_uri.clone(),
+            reason: String::from("a SIP account can't call a Ring id"),
+        }));
+        assert!(parse_destination("SIP", "").is_err());
+        assert!(parse_destination("SIP", "mailto:bob@example.com").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/**
+ * A device linked to a Ring account
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    pub id: String,
+    pub name: String,
+    // If it is the device running the daemon
+    pub current: bool,
+}
+
+/**
+ * @param devices deviceId -> name, as returned by getKnownRingDevices
+ * @param current_id id of the device running the daemon
+ * @return the devices, the current one first, then by name
+ */
+pub fn devices_from_daemon(devices: &HashMap<String, String>, current_id: &str) -> Vec<Device> {
+    let mut result: Vec<Device> = devices.iter().map(|(id, name)| Device {
+        id: id.clone(),
+        name: name.clone(),
+        current: id == current_id,
+    }).collect();
+    result.sort_by(|a, b| b.current.cmp(&a.current).then(a.name.cmp(&b.name)).then(a.id.cmp(&b.id)));
+    result
+}
+
+/**
+ * Result of exportOnRing, as told by exportOnRingEnded
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportOnRingStatus {
+    Success,
+    WrongPassword,
+    NetworkError,
+    Unknown(i32),
+}
+
+impl ExportOnRingStatus {
+    /**
+     * @param status as sent by the daemon
+     * @return the status
+     */
+    pub fn from_daemon(status: i32) -> ExportOnRingStatus {
+        match status {
+            0 => ExportOnRingStatus::Success,
+            1 => ExportOnRingStatus::WrongPassword,
+            2 => ExportOnRingStatus::NetworkError,
+            other => ExportOnRingStatus::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for ExportOnRingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExportOnRingStatus::Success => write!(f, "account exported"),
+            ExportOnRingStatus::WrongPassword => write!(f, "wrong password"),
+            ExportOnRingStatus::NetworkError => write!(f, "network error"),
+            ExportOnRingStatus::Unknown(status) => write!(f, "unknown status {}", status),
+        }
+    }
+}
+
+/**
+ * Result of revokeDevice, as told by deviceRevocationEnded
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RevocationStatus {
+    Success,
+    WrongPassword,
+    UnknownDevice,
+    Unknown(i32),
+}
+
+impl RevocationStatus {
+    /**
+     * @param status as sent by the daemon
+     * @return the status
+     */
+    pub fn from_daemon(status: i32) -> RevocationStatus {
+        match status {
+            0 => RevocationStatus::Success,
+            1 => RevocationStatus::WrongPassword,
+            2 => RevocationStatus::UnknownDevice,
+            other => RevocationStatus::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for RevocationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RevocationStatus::Success => write!(f, "device revoked"),
+            RevocationStatus::WrongPassword => write!(f, "wrong password"),
+            RevocationStatus::UnknownDevice => write!(f, "unknown device"),
+            RevocationStatus::Unknown(status) => write!(f, "unknown status {}", status),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use ring::api::account::RegistrationState;
+use ring::api::call::CallState;
+use ring::api::conference::ConferenceState;
+use ring::api::device::{ExportOnRingStatus, RevocationStatus};
+use ring::api::interaction::{Interaction, MessageStatus};
+use ring::api::name::{LookupStatus, NameRegistrationState};
+use ring::api::transfer::TransferStatus;
+
+use std::collections::HashMap;
+
+/**
+ * Something happened on the daemon's side.
+ * Published by the Manager to its subscribers.
+ */
+#[derive(Debug, Clone)]
+pub enum RingEvent {
+    IncomingMessage {
+        account_id: String,
+        interaction: Interaction,
+    },
+    IncomingTrustRequest {
+        account_id: String,
+        from: String,
+    },
+    AccountsChanged,
+    RegistrationStateChanged {
+        account_id: String,
+        state: RegistrationState,
+    },
+    MessageStatusChanged {
+        account_id: String,
+        message_id: u64,
+        status: MessageStatus,
+    },
+    RegisteredNameFound {
+        account_id: String,
+        status: LookupStatus,
+        address: String,
+        name: String,
+    },
+    NameRegistrationEnded {
+        account_id: String,
+        state: NameRegistrationState,
+        name: String,
+    },
+    IncomingCall {
+        account_id: String,
+        call_id: String,
+        from: String,
+    },
+    CallStateChanged {
+        call_id: String,
+        state: CallState,
+    },
+    // The new conference is found by refreshing them
+    ConferenceCreated,
+    ConferenceChanged {
+        conference_id: String,
+        state: ConferenceState,
+    },
+    ConferenceRemoved {
+        conference_id: String,
+    },
+    DataTransferEvent {
+        transfer_id: u64,
+        status: TransferStatus,
+    },
+    KnownDevicesChanged {
+        account_id: String,
+        // deviceId -> name
+        devices: HashMap<String, String>,
+    },
+    DeviceRevocationEnded {
+        account_id: String,
+        device_id: String,
+        status: RevocationStatus,
+    },
+    ExportOnRingEnded {
+        account_id: String,
+        status: ExportOnRingStatus,
+        pin: String,
+    },
+    // Not a signal: sent by the Manager once an account imported with a PIN
+    // is registered, or failed to be
+    AccountImportEnded {
+        account_id: String,
+        registration: RegistrationState,
+    },
+}
@@ -1,16 +1,187 @@
+use std::collections::HashMap;
 use std::fmt;
 use time::Tm;
 
-#[derive(Clone)]
+// MIME types of the payloads understood by this client
+pub const TEXT_PLAIN: &str = "text/plain";
+pub const TEXT_HTML: &str = "text/html";
+pub const GEO_JSON: &str = "application/geo+json";
+pub const VCARD: &str = "text/vcard";
+// Profiles sent by Ring clients, as "x-ring/ring.profile.vcard;id=..,part=..,of=.."
+pub const RING_PROFILE: &str = "x-ring/ring.profile.vcard";
+
+/**
+ * Who wrote an interaction
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+/**
+ * Delivery of an outgoing interaction
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageStatus {
+    // Not yet accepted by the daemon
+    Sending,
+    // Accepted by the daemon, which sends it
+    Sent,
+    // Received by the peer
+    Delivered,
+    Read,
+    Failure,
+}
+
+impl MessageStatus {
+    /**
+     * @param status as sent by accountMessageStatusChanged (0 unknown, 1 sending, 2 sent, 3 read, 4 failure)
+     * @return the status, None if unknown
+     */
+    pub fn from_daemon(status: i32) -> Option<MessageStatus> {
+        match status {
+            1 => Some(MessageStatus::Sent),
+            // The daemon says SENT once the peer acknowledged the message
+            2 => Some(MessageStatus::Delivered),
+            3 => Some(MessageStatus::Read),
+            4 => Some(MessageStatus::Failure),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for MessageStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match *self {
+            MessageStatus::Sending => "sending",
+            MessageStatus::Sent => "sent",
+            MessageStatus::Delivered => "delivered",
+            MessageStatus::Read => "read",
+            MessageStatus::Failure => "failure",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Interaction
 {
+    // id given by the daemon, 0 if unknown
+    pub id: u64,
     pub author_ring_id: String,
-    pub body: String,
-    pub time: Tm
+    // The contact this interaction is exchanged with
+    pub peer_ring_id: String,
+    // MIME type -> content
+    pub payloads: HashMap<String, String>,
+    pub time: Tm,
+    pub direction: Direction,
+    // Always Delivered for incoming interactions
+    pub status: MessageStatus,
 }
+
+impl Interaction {
+    /**
+     * @param self
+     * @param mime_type
+     * @return the payload of this type, if any
+     */
+    pub fn payload(&self, mime_type: &str) -> Option<&str> {
+        self.payloads.get(mime_type).map(|p| p.as_str())
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.payload(TEXT_PLAIN)
+    }
+
+    pub fn html(&self) -> Option<&str> {
+        self.payload(TEXT_HTML)
+    }
+
+    /**
+     * @param self
+     * @return the shared location, as a GeoJSON document
+     */
+    pub fn geo_json(&self) -> Option<&str> {
+        self.payload(GEO_JSON)
+    }
+
+    /**
+     * @param self
+     * @return the shared contact card, or the part of a Ring profile
+     */
+    pub fn vcard(&self) -> Option<&str> {
+        if let Some(vcard) = self.payload(VCARD) {
+            return Some(vcard);
+        }
+        self.payloads.iter()
+            .find(|&(mime_type, _)| mime_type.starts_with(RING_PROFILE))
+            .map(|(_, vcard)| vcard.as_str())
+    }
+
+    /**
+     * What to show of this interaction in a single line
+     * @param self
+     * @return the text, else a description of the payloads
+     */
+    pub fn summary(&self) -> String {
+        if let Some(text) = self.text() {
+            return text.to_string();
+        }
+        if self.html().is_some() {
+            return String::from("[html message]");
+        }
+        if self.geo_json().is_some() {
+            return String::from("[location]");
+        }
+        if self.vcard().is_some() {
+            return String::from("[contact card]");
+        }
+        let mut types: Vec<&str> = self.payloads.keys().map(|t| t.as_str()).collect();
+        types.sort();
+        format!("[{}]", types.join(", "))
+    }
+}
+
 // Used for println!
 impl fmt::Display for Interaction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}: {}", self.author_ring_id, self.body)
+        write!(f, "{}: {}", self.author_ring_id, self.summary())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::api::interaction::{Direction, Interaction, MessageStatus};
+    use time;
+
+    fn interaction(payloads: &[(&str, &str)]) -> Interaction {
+        Interaction {
+            id: 0,
+            author_ring_id: String::from("a1b2c3"),
+            peer_ring_id: String::from("a1b2c3"),
+            payloads: payloads.iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect(),
+            time: time::now(),
+            direction: Direction::Incoming,
+            status: MessageStatus::Delivered,
+        }
+    }
+
+    #[test]
+    fn payloads_are_found_by_type() {
+        let i = interaction(&[("text/plain", "hi"), ("text/html", "<b>hi</b>")]);
+        assert_eq!(i.text(), Some("hi"));
+        assert_eq!(i.html(), Some("<b>hi</b>"));
+        assert_eq!(i.geo_json(), None);
+        assert_eq!(i.summary(), "hi");
+
+        let i = interaction(&[("x-ring/ring.profile.vcard;id=1,part=1,of=1", "BEGIN:VCARD")]);
+        assert_eq!(i.vcard(), Some("BEGIN:VCARD"));
+        assert_eq!(i.summary(), "[contact card]");
+
+        let i = interaction(&[("application/geo+json", "{}")]);
+        assert_eq!(i.summary(), "[location]");
+
+        assert_eq!(interaction(&[("image/png", "")]).summary(), "[image/png]");
     }
 }
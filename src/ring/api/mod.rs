@@ -1,2 +1,11 @@
 pub mod account;
+pub mod account_details;
+pub mod call;
+pub mod conference;
+pub mod conversation;
+pub mod destination;
+pub mod device;
+pub mod event;
 pub mod interaction;
+pub mod name;
+pub mod transfer;
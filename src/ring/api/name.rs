@@ -0,0 +1,78 @@
+use std::fmt;
+
+/**
+ * Result of lookupName and lookupAddress, as told by registeredNameFound
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LookupStatus {
+    Found,
+    InvalidName,
+    NotFound,
+    Error,
+}
+
+impl LookupStatus {
+    /**
+     * @param status as sent by the daemon
+     * @return the status
+     */
+    pub fn from_daemon(status: i32) -> LookupStatus {
+        match status {
+            0 => LookupStatus::Found,
+            1 => LookupStatus::InvalidName,
+            2 => LookupStatus::NotFound,
+            _ => LookupStatus::Error,
+        }
+    }
+}
+
+/**
+ * Result of registerName, as told by nameRegistrationEnded
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NameRegistrationState {
+    Success,
+    WrongPassword,
+    InvalidName,
+    AlreadyTaken,
+    NetworkError,
+    Unknown(i32),
+}
+
+impl NameRegistrationState {
+    /**
+     * @param state as sent by the daemon
+     * @return the state
+     */
+    pub fn from_daemon(state: i32) -> NameRegistrationState {
+        match state {
+            0 => NameRegistrationState::Success,
+            1 => NameRegistrationState::WrongPassword,
+            2 => NameRegistrationState::InvalidName,
+            3 => NameRegistrationState::AlreadyTaken,
+            4 => NameRegistrationState::NetworkError,
+            other => NameRegistrationState::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for NameRegistrationState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NameRegistrationState::Success => write!(f, "name registered"),
+            NameRegistrationState::WrongPassword => write!(f, "wrong password"),
+            NameRegistrationState::InvalidName => write!(f, "invalid name"),
+            NameRegistrationState::AlreadyTaken => write!(f, "name already taken"),
+            NameRegistrationState::NetworkError => write!(f, "network error"),
+            NameRegistrationState::Unknown(state) => write!(f, "unknown state {}", state),
+        }
+    }
+}
+
+/**
+ * @param id
+ * @return if id looks like a Ring id (40 hexadecimal digits), and not like a name
+ */
+pub fn is_ring_id(id: &str) -> bool {
+    id.len() == 40 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
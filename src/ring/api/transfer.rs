@@ -0,0 +1,178 @@
+use ring::api::interaction::Direction;
+use std::fmt;
+
+/**
+ * DataTransferInfo as exchanged with the daemon:
+ * (accountId, lastEvent, flags, totalSize, bytesProgress, peer, displayName, path, mimetype)
+ */
+pub type DataTransferInfo = (String, u32, u32, i64, i64, String, String, String, String);
+
+// Set in the flags of incoming transfers
+const FLAG_INCOMING: u32 = 1;
+
+// DataTransferError returned by the daemon
+pub const TRANSFER_SUCCESS: u32 = 0;
+pub const TRANSFER_UNKNOWN: u32 = 1;
+pub const TRANSFER_IO_ERROR: u32 = 2;
+pub const TRANSFER_INVALID_ARGUMENT: u32 = 3;
+
+/**
+ * State of a file transfer, as told by dataTransferEvent
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferStatus {
+    Invalid,
+    Created,
+    Unsupported,
+    WaitPeerAcceptance,
+    WaitHostAcceptance,
+    Ongoing,
+    Finished,
+    ClosedByHost,
+    ClosedByPeer,
+    InvalidPathname,
+    UnjoinablePeer,
+    Unknown(u32),
+}
+
+impl TransferStatus {
+    /**
+     * @param code as sent by the daemon
+     * @return the status
+     */
+    pub fn from_daemon(code: u32) -> TransferStatus {
+        match code {
+            0 => TransferStatus::Invalid,
+            1 => TransferStatus::Created,
+            2 => TransferStatus::Unsupported,
+            3 => TransferStatus::WaitPeerAcceptance,
+            4 => TransferStatus::WaitHostAcceptance,
+            5 => TransferStatus::Ongoing,
+            6 => TransferStatus::Finished,
+            7 => TransferStatus::ClosedByHost,
+            8 => TransferStatus::ClosedByPeer,
+            9 => TransferStatus::InvalidPathname,
+            10 => TransferStatus::UnjoinablePeer,
+            other => TransferStatus::Unknown(other),
+        }
+    }
+
+    /**
+     * @param self
+     * @return the code used by the daemon
+     */
+    pub fn to_daemon(self) -> u32 {
+        match self {
+            TransferStatus::Invalid => 0,
+            TransferStatus::Created => 1,
+            TransferStatus::Unsupported => 2,
+            TransferStatus::WaitPeerAcceptance => 3,
+            TransferStatus::WaitHostAcceptance => 4,
+            TransferStatus::Ongoing => 5,
+            TransferStatus::Finished => 6,
+            TransferStatus::ClosedByHost => 7,
+            TransferStatus::ClosedByPeer => 8,
+            TransferStatus::InvalidPathname => 9,
+            TransferStatus::UnjoinablePeer => 10,
+            TransferStatus::Unknown(code) => code,
+        }
+    }
+
+    /**
+     * @param self
+     * @return if nothing will happen anymore for the transfer
+     */
+    pub fn is_over(&self) -> bool {
+        !matches!(*self, TransferStatus::Created | TransferStatus::WaitPeerAcceptance
+                  | TransferStatus::WaitHostAcceptance | TransferStatus::Ongoing)
+    }
+}
+
+impl fmt::Display for TransferStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransferStatus::Invalid => write!(f, "invalid"),
+            TransferStatus::Created => write!(f, "created"),
+            TransferStatus::Unsupported => write!(f, "unsupported"),
+            TransferStatus::WaitPeerAcceptance => write!(f, "waiting for the peer"),
+            TransferStatus::WaitHostAcceptance => write!(f, "to accept"),
+            TransferStatus::Ongoing => write!(f, "ongoing"),
+            TransferStatus::Finished => write!(f, "finished"),
+            TransferStatus::ClosedByHost => write!(f, "cancelled"),
+            TransferStatus::ClosedByPeer => write!(f, "cancelled by the peer"),
+            TransferStatus::InvalidPathname => write!(f, "invalid path"),
+            TransferStatus::UnjoinablePeer => write!(f, "peer unreachable"),
+            TransferStatus::Unknown(code) => write!(f, "unknown status {}", code),
+        }
+    }
+}
+
+/**
+ * A file sent to, or received from, a contact
+ */
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub id: u64,
+    pub account_id: String,
+    pub peer: String,
+    pub direction: Direction,
+    pub status: TransferStatus,
+    // Name of the file, as shown to the peer
+    pub display_name: String,
+    // Where the file is read or written, empty until an incoming transfer is accepted
+    pub path: String,
+    pub mime_type: String,
+    pub total_size: i64,
+    pub progress: i64,
+}
+
+impl Transfer {
+    /**
+     * @param id of the transfer
+     * @param info as returned by dataTransferInfo
+     * @return the transfer
+     */
+    pub fn from_info(id: u64, info: DataTransferInfo) -> Transfer {
+        let (account_id, last_event, flags, total_size, progress, peer, display_name, path, mime_type) = info;
+        Transfer {
+            id,
+            account_id,
+            peer,
+            direction: if flags & FLAG_INCOMING != 0 {Direction::Incoming} else {Direction::Outgoing},
+            status: TransferStatus::from_daemon(last_event),
+            display_name,
+            path,
+            mime_type,
+            total_size,
+            progress,
+        }
+    }
+
+    /**
+     * @return the info returned with the errors, when there is no transfer
+     */
+    pub fn empty_info() -> DataTransferInfo {
+        (String::new(), 0, 0, 0, 0, String::new(), String::new(), String::new(), String::new())
+    }
+
+    /**
+     * @param self
+     * @return the info to give to the daemon
+     */
+    pub fn to_info(&self) -> DataTransferInfo {
+        let flags = if self.direction == Direction::Incoming {FLAG_INCOMING} else {0};
+        (self.account_id.clone(), self.status.to_daemon(), flags, self.total_size, self.progress,
+         self.peer.clone(), self.display_name.clone(), self.path.clone(), self.mime_type.clone())
+    }
+
+    /**
+     * @param self
+     * @return the part of the file transferred, between 0 and 1
+     */
+    pub fn ratio(&self) -> f64 {
+        if self.total_size <= 0 {
+            return if self.status == TransferStatus::Finished {1.} else {0.};
+        }
+        (self.progress as f64 / self.total_size as f64).clamp(0., 1.)
+    }
+}
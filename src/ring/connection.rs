@@ -0,0 +1,198 @@
+use ring::error::RingError;
+
+use dbus::{Connection, BusType, Message};
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+// A method call for the thread owning the connection, and where to send its reply
+struct Request {
+    msg: Message,
+    timeout_ms: i32,
+    reply: Sender<Result<Message, RingError>>,
+}
+
+/**
+ * Long-lived connection to the bus where the daemon lives.
+ * dbus connections can't move between threads, so the connection lives
+ * on its own thread, which receives the method calls. It is opened on the
+ * first call and re-opened after the bus dropped it, so the Manager doesn't
+ * open a new connection for each method call.
+ */
+pub struct BusConnection {
+    address: Option<String>,
+    // Method calls for the connection thread, started on the first call
+    requests: Mutex<Option<Sender<Request>>>,
+}
+
+impl BusConnection {
+    /**
+     * Connection to the session bus
+     * @return the (not yet opened) connection
+     */
+    pub fn session() -> BusConnection {
+        BusConnection {
+            address: None,
+            requests: Mutex::new(None),
+        }
+    }
+
+    /**
+     * Connection to a bus by its address
+     * @param address of the bus
+     * @return the (not yet opened) connection
+     */
+    #[cfg(test)]
+    pub fn with_address(address: &str) -> BusConnection {
+        BusConnection {
+            address: Some(address.to_string()),
+            requests: Mutex::new(None),
+        }
+    }
+
+    /**
+     * Open a new private connection to the same bus, to use on the calling thread.
+     * Used to listen signals without blocking method calls.
+     * @param self
+     * @return the new connection
+     */
+    pub fn open(&self) -> Result<Connection, RingError> {
+        open_connection(&self.address)
+    }
+
+    /**
+     * Send a method call on the shared connection and wait for its reply
+     * @param self
+     * @param msg the method call
+     * @param timeout_ms
+     * @return the reply
+     */
+    pub fn call(&self, msg: Message, timeout_ms: i32) -> Result<Message, RingError> {
+        let mut requests = self.requests.lock().unwrap();
+        if requests.is_none() {
+            let (tx, rx) = channel();
+            let address = self.address.clone();
+            thread::spawn(move || serve(address, rx));
+            *requests = Some(tx);
+        }
+        let (reply, result) = channel();
+        let sent = requests.as_ref().map(|requests| requests.send(Request {
+            msg,
+            timeout_ms,
+            reply,
+        }).is_ok()).unwrap_or(false);
+        match result.recv() {
+            Ok(result) if sent => result,
+            _ => {
+                // The thread is gone, the next call starts a new one
+                *requests = None;
+                Err(RingError::DaemonUnreachable(String::from("bus connection closed")))
+            }
+        }
+    }
+}
+
+/**
+ * @param address of the bus, the session bus if None
+ * @return a new private connection
+ */
+fn open_connection(address: &Option<String>) -> Result<Connection, RingError> {
+    let conn = match *address {
+        Some(ref address) => Connection::open_private(address).and_then(|conn| {
+            conn.register()?;
+            Ok(conn)
+        }),
+        None => Connection::get_private(BusType::Session),
+    };
+    conn.map_err(|e| RingError::DaemonUnreachable(e.message().unwrap_or("").to_string()))
+}
+
+/**
+ * Own the connection and send the method calls on it, until the BusConnection is dropped
+ * @param address of the bus, the session bus if None
+ * @param requests method calls to send
+ */
+fn serve(address: Option<String>, requests: Receiver<Request>) {
+    let mut conn: Option<Connection> = None;
+    for request in requests {
+        if conn.is_none() {
+            match open_connection(&address) {
+                Ok(opened) => conn = Some(opened),
+                Err(e) => {
+                    let _ = request.reply.send(Err(e));
+                    continue;
+                }
+            }
+        }
+        let result = match conn.as_ref().unwrap().send_with_reply_and_block(request.msg, request.timeout_ms) {
+            Ok(reply) => Ok(reply),
+            Err(e) => {
+                // A missing daemon leaves the bus usable, only a dropped bus needs a new connection
+                let lost = matches!(e.name(), Some("org.freedesktop.DBus.Error.Disconnected")
+                                              | Some("org.freedesktop.DBus.Error.NoServer"));
+                if lost {
+                    warn!("Lost connection to the bus: {}", e.message().unwrap_or(""));
+                    conn = None;
+                }
+                Err(RingError::from(e))
+            }
+        };
+        let _ = request.reply.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dbus::Message;
+    use ring::connection::BusConnection;
+    use ring::error::RingError;
+    use std::env;
+    use std::fs;
+    use std::process::{self, Child, Command};
+    use std::thread;
+    use std::time::Duration;
+
+    /**
+     * Launch a bus listening on a fixed socket, so it can be restarted at the same address
+     */
+    fn start_bus(socket: &str) -> Child {
+        let _ = fs::remove_file(socket);
+        let bus = Command::new("dbus-daemon")
+                          .args(["--session", "--nofork", &format!("--address=unix:path={}", socket)])
+                          .spawn()
+                          .expect("Can't launch dbus-daemon");
+        for _ in 0..100 {
+            if fs::metadata(socket).is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        bus
+    }
+
+    fn get_id(connection: &BusConnection) -> Result<Message, RingError> {
+        let msg = Message::new_method_call("org.freedesktop.DBus", "/org/freedesktop/DBus",
+                                           "org.freedesktop.DBus", "GetId").unwrap();
+        connection.call(msg, 2000)
+    }
+
+    #[test]
+    fn lost_bus_is_reconnected() {
+        let socket = env::temp_dir().join(format!("rori-bus-{}", process::id()));
+        let socket = socket.to_string_lossy().to_string();
+        let mut bus = start_bus(&socket);
+        let connection = BusConnection::with_address(&format!("unix:path={}", socket));
+        assert!(get_id(&connection).is_ok());
+
+        // The connection is dropped with the bus, and opened again on the next call
+        bus.kill().unwrap();
+        bus.wait().unwrap();
+        assert!(get_id(&connection).is_err());
+
+        let mut bus = start_bus(&socket);
+        assert!(get_id(&connection).is_ok());
+        bus.kill().unwrap();
+        bus.wait().unwrap();
+        let _ = fs::remove_file(&socket);
+    }
+}
@@ -0,0 +1,308 @@
+use ring::api::event::RingEvent;
+use ring::api::transfer::DataTransferInfo;
+use ring::error::RingError;
+
+use std::collections::HashMap;
+
+/**
+ * Where the events of a daemon come from
+ */
+pub trait EventSource {
+    /**
+     * Wait for the next events
+     * @param self
+     * @param timeout_ms how long to wait for an event
+     * @return the events received, may be empty
+     */
+    fn poll(&mut self, timeout_ms: i32) -> Vec<RingEvent>;
+}
+
+/**
+ * Operations a Ring daemon offers to the Manager. The Manager keeps the
+ * client logic (building accounts, formatting destinations...) and uses
+ * this trait to reach the daemon, whatever the backend is.
+ */
+pub trait RingDaemon {
+// Accounts
+
+    /**
+     * @return ids of the accounts
+     */
+    fn get_account_list(&self) -> Result<Vec<String>, RingError>;
+
+    /**
+     * @param account_id
+     * @return the raw details of the account
+     */
+    fn get_account_details(&self, account_id: &str) -> Result<HashMap<String, String>, RingError>;
+
+    /**
+     * @param account_id
+     * @return the details of the account which change at runtime (registration...)
+     */
+    fn get_volatile_account_details(&self, account_id: &str) -> Result<HashMap<String, String>, RingError>;
+
+    /**
+     * Replace the details of an account
+     * @param account_id
+     * @param details all the details of the account
+     */
+    fn set_account_details(&self, account_id: &str, details: HashMap<String, String>) -> Result<(), RingError>;
+
+    /**
+     * Create an account
+     * @param details of the new account
+     * @return the new account id
+     */
+    fn add_account(&self, details: HashMap<String, String>) -> Result<String, RingError>;
+
+    fn rm_account(&self, account_id: &str) -> Result<(), RingError>;
+
+    fn enable_account(&self, account_id: &str, enable: bool) -> Result<(), RingError>;
+
+    /**
+     * Write the archive of an account, to import it on another device
+     * @param account_id
+     * @param path where to write the archive
+     * @param password of the account archive
+     * @return if the archive was written
+     */
+    fn export_to_file(&self, account_id: &str, path: &str, password: &str) -> Result<bool, RingError>;
+
+    /**
+     * @param account_id
+     * @param old_password current password of the archive, empty if none
+     * @param new_password
+     * @return if the password was changed
+     */
+    fn change_account_password(&self, account_id: &str, old_password: &str, new_password: &str) -> Result<bool, RingError>;
+
+// Contacts
+
+    /**
+     * @param account_id
+     * @return ring ids of the contacts
+     */
+    fn get_contacts(&self, account_id: &str) -> Result<Vec<String>, RingError>;
+
+    fn add_contact(&self, account_id: &str, contact: &str) -> Result<(), RingError>;
+
+    fn rm_contact(&self, account_id: &str, contact: &str, banned: bool) -> Result<(), RingError>;
+
+// Trust requests
+
+    /**
+     * @param account_id
+     * @return ring ids of the pending requests
+     */
+    fn get_requests(&self, account_id: &str) -> Result<Vec<String>, RingError>;
+
+    fn send_trust_request(&self, from: &str, destination: &str) -> Result<(), RingError>;
+
+    /**
+     * Accept or discard a pending request
+     * @return if the request was found
+     */
+    fn accept_request(&self, account_id: &str, from: &str, accept: bool) -> Result<bool, RingError>;
+
+// Messaging
+
+    /**
+     * @param from account id
+     * @param destination ring id
+     * @param payloads mime type -> content
+     * @return the message id
+     */
+    fn send_text_message(&self, from: &str, destination: &str,
+                         payloads: HashMap<String, String>) -> Result<u64, RingError>;
+
+// Name server
+
+    /**
+     * Ask the name server which address has a name. Answered by registeredNameFound.
+     * @param account_id
+     * @param name_server empty for the default one
+     * @param name
+     * @return if the lookup started
+     */
+    fn lookup_name(&self, account_id: &str, name_server: &str, name: &str) -> Result<bool, RingError>;
+
+    /**
+     * Ask the name server which name has an address. Answered by registeredNameFound.
+     * @param account_id
+     * @param name_server empty for the default one
+     * @param address ring id
+     * @return if the lookup started
+     */
+    fn lookup_address(&self, account_id: &str, name_server: &str, address: &str) -> Result<bool, RingError>;
+
+    /**
+     * Register a name for an account. Answered by nameRegistrationEnded.
+     * @param account_id
+     * @param password of the account archive
+     * @param name
+     * @return if the registration started
+     */
+    fn register_name(&self, account_id: &str, password: &str, name: &str) -> Result<bool, RingError>;
+
+// Devices
+
+    /**
+     * @param account_id
+     * @return deviceId -> name of the devices linked to the account
+     */
+    fn get_known_ring_devices(&self, account_id: &str) -> Result<HashMap<String, String>, RingError>;
+
+    /**
+     * Unlink a device from an account. Answered by deviceRevocationEnded.
+     * @param account_id
+     * @param password of the account archive
+     * @param device_id
+     * @return if the revocation started
+     */
+    fn revoke_device(&self, account_id: &str, password: &str, device_id: &str) -> Result<bool, RingError>;
+
+    /**
+     * Publish the account on the DHT to link a new device. Answered by exportOnRingEnded with a PIN.
+     * @param account_id
+     * @param password of the account archive
+     * @return if the export started
+     */
+    fn export_on_ring(&self, account_id: &str, password: &str) -> Result<bool, RingError>;
+
+// Calls
+
+    /**
+     * @param account_id
+     * @param destination uri to call
+     * @return the call id
+     */
+    fn place_call(&self, account_id: &str, destination: &str) -> Result<String, RingError>;
+
+    // The following calls return false if the call is unknown
+
+    fn accept(&self, call_id: &str) -> Result<bool, RingError>;
+
+    fn refuse(&self, call_id: &str) -> Result<bool, RingError>;
+
+    fn hang_up(&self, call_id: &str) -> Result<bool, RingError>;
+
+    fn hold(&self, call_id: &str) -> Result<bool, RingError>;
+
+    fn unhold(&self, call_id: &str) -> Result<bool, RingError>;
+
+    /**
+     * @param call_id
+     * @param media_type AUDIO or VIDEO
+     * @param mute
+     */
+    fn mute_local_media(&self, call_id: &str, media_type: &str, mute: bool) -> Result<bool, RingError>;
+
+    /**
+     * Blind transfer of a call
+     * @param call_id
+     * @param to uri of the new peer
+     */
+    fn transfer(&self, call_id: &str, to: &str) -> Result<bool, RingError>;
+
+    /**
+     * Send a tone in the current call
+     * @param key 0-9, *, # or A-D
+     */
+    fn play_dtmf(&self, key: &str) -> Result<(), RingError>;
+
+    /**
+     * @return ids of the current calls
+     */
+    fn get_call_list(&self) -> Result<Vec<String>, RingError>;
+
+    /**
+     * @param call_id
+     * @return the raw details of the call, empty if unknown
+     */
+    fn get_call_details(&self, call_id: &str) -> Result<HashMap<String, String>, RingError>;
+
+// Conferences
+
+    /**
+     * Merge two calls, or a call and the conference of the other
+     * @param sel_call_id
+     * @param drag_call_id
+     */
+    fn join_participant(&self, sel_call_id: &str, drag_call_id: &str) -> Result<bool, RingError>;
+
+    /**
+     * Call several peers in a new conference
+     * @param participants uris to call
+     */
+    fn create_conf_from_participant_list(&self, participants: Vec<String>) -> Result<(), RingError>;
+
+    fn add_participant(&self, call_id: &str, conference_id: &str) -> Result<bool, RingError>;
+
+    /**
+     * Take a call out of its conference
+     */
+    fn detach_participant(&self, call_id: &str) -> Result<bool, RingError>;
+
+    fn hang_up_conference(&self, conference_id: &str) -> Result<bool, RingError>;
+
+    /**
+     * @return ids of the conferences
+     */
+    fn get_conference_list(&self) -> Result<Vec<String>, RingError>;
+
+    /**
+     * @return ids of the calls in the conference
+     */
+    fn get_participant_list(&self, conference_id: &str) -> Result<Vec<String>, RingError>;
+
+    /**
+     * @return the raw details of the conference, empty if unknown
+     */
+    fn get_conference_details(&self, conference_id: &str) -> Result<HashMap<String, String>, RingError>;
+
+// File transfers
+// Methods returning an u32 give the DataTransferError of the daemon (see TRANSFER_SUCCESS...)
+
+    /**
+     * @return ids of the transfers known by the daemon
+     */
+    fn data_transfer_list(&self) -> Result<Vec<u64>, RingError>;
+
+    /**
+     * Offer a file to a peer
+     * @param info account, peer, path, display name and mime type of the file
+     * @return (error, id of the new transfer)
+     */
+    fn send_file(&self, info: DataTransferInfo) -> Result<(u32, u64), RingError>;
+
+    /**
+     * @return (error, info of the transfer)
+     */
+    fn data_transfer_info(&self, id: u64) -> Result<(u32, DataTransferInfo), RingError>;
+
+    /**
+     * @return (error, total size, bytes transferred)
+     */
+    fn data_transfer_bytes_progress(&self, id: u64) -> Result<(u32, i64, i64), RingError>;
+
+    /**
+     * Accept an incoming file
+     * @param id of the transfer
+     * @param file_path where to write the file
+     * @param offset where to resume the transfer, 0 to start it
+     */
+    fn accept_file_transfer(&self, id: u64, file_path: &str, offset: i64) -> Result<u32, RingError>;
+
+    fn cancel_data_transfer(&self, id: u64) -> Result<u32, RingError>;
+
+// Signals
+
+    /**
+     * Start listening the daemon's events
+     * @return None if this daemon doesn't emit events
+     */
+    fn listener(&self) -> Result<Option<Box<dyn EventSource>>, RingError> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,423 @@
+use ring::api::transfer::DataTransferInfo;
+use ring::connection::BusConnection;
+use ring::daemon::{EventSource, RingDaemon};
+use ring::error::RingError;
+use ring::signals::SignalListener;
+
+use dbus::Message;
+use dbus::arg::{Array, Dict};
+use std::collections::HashMap;
+use std::env;
+
+/**
+ * Names used by a daemon on the bus. Old daemons publish cx.ring.Ring,
+ * newer (Jami) ones net.jami.daemon.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct DaemonNames {
+    pub service: String,
+    pub configuration_path: String,
+    pub configuration_iface: String,
+    pub call_path: String,
+    pub call_iface: String,
+}
+
+impl DaemonNames {
+    /**
+     * Build the names from the service name and the objects prefix
+     * @param service e.g. cx.ring.Ring
+     * @param prefix e.g. /cx/ring/Ring
+     */
+    fn new(service: &str, prefix: &str) -> DaemonNames {
+        DaemonNames {
+            service: service.to_string(),
+            configuration_path: format!("{}/ConfigurationManager", prefix),
+            configuration_iface: format!("{}.ConfigurationManager", service),
+            call_path: format!("{}/CallManager", prefix),
+            call_iface: format!("{}.CallManager", service),
+        }
+    }
+
+    pub fn ring() -> DaemonNames {
+        DaemonNames::new("cx.ring.Ring", "/cx/ring/Ring")
+    }
+
+    pub fn jami() -> DaemonNames {
+        DaemonNames::new("net.jami.daemon", "/net/jami/daemon")
+    }
+
+    /**
+     * Names from the RURING_DAEMON environment variable ("ring" or "jami")
+     * @return None if not configured
+     */
+    pub fn from_env() -> Option<DaemonNames> {
+        match env::var("RURING_DAEMON").ok().as_deref() {
+            Some("ring") | Some("cx.ring.Ring") => Some(DaemonNames::ring()),
+            Some("jami") | Some("net.jami.daemon") => Some(DaemonNames::jami()),
+            Some(other) => {
+                warn!("Unknown RURING_DAEMON {}, probing the bus", other);
+                None
+            },
+            None => None
+        }
+    }
+}
+
+/**
+ * The Ring daemon reached through D-Bus
+ */
+pub struct DBusDaemon {
+    bus: BusConnection,
+    names: DaemonNames,
+}
+
+impl DBusDaemon {
+    /**
+     * Use the daemon configured by RURING_DAEMON, else the one found on the bus
+     * @param bus where the daemon lives
+     */
+    pub fn new(bus: BusConnection) -> DBusDaemon {
+        let names = match DaemonNames::from_env() {
+            Some(names) => names,
+            None => DBusDaemon::probe(&bus),
+        };
+        info!("Using daemon {}", names.service);
+        DBusDaemon::with_names(bus, names)
+    }
+
+    pub fn with_names(bus: BusConnection, names: DaemonNames) -> DBusDaemon {
+        DBusDaemon {
+            bus,
+            names,
+        }
+    }
+
+    /**
+     * Find which daemon is on the bus. Jami is preferred if both are.
+     * @param bus
+     * @return names of the daemon found, Ring ones if none is running
+     */
+    fn probe(bus: &BusConnection) -> DaemonNames {
+        for names in [DaemonNames::jami(), DaemonNames::ring()] {
+            let has_owner = Message::new_method_call("org.freedesktop.DBus", "/org/freedesktop/DBus",
+                                                     "org.freedesktop.DBus", "NameHasOwner")
+                .map_err(RingError::MethodMissing)
+                .and_then(|msg| bus.call(msg.append1(&*names.service), 2000))
+                .and_then(|reply| Ok(reply.read1::<bool>()?));
+            match has_owner {
+                Ok(true) => return names,
+                Ok(false) => {},
+                Err(e) => warn!("Can't probe {}: {}", names.service, e),
+            }
+        }
+        DaemonNames::ring()
+    }
+
+    /**
+     * Call a method of the daemon and wait for its reply
+     * @param self
+     * @param path object path of the daemon's manager
+     * @param iface interface of the daemon's manager
+     * @param method to call
+     * @param build appends the arguments to the method call
+     * @return the reply, or why the daemon didn't give one
+     */
+    fn call<F>(&self, path: &str, iface: &str, method: &str, build: F) -> Result<Message, RingError>
+        where F: FnOnce(Message) -> Message
+    {
+        let dbus_msg = match Message::new_method_call(&*self.names.service, path, iface, method) {
+            Ok(dbus_msg) => dbus_msg,
+            Err(_) => {
+                error!("{} call fails. Please verify daemon's API.", method);
+                return Err(RingError::MethodMissing(method.to_string()));
+            }
+        };
+        self.bus.call(build(dbus_msg), 2000)
+    }
+
+    fn call_configuration<F>(&self, method: &str, build: F) -> Result<Message, RingError>
+        where F: FnOnce(Message) -> Message
+    {
+        self.call(&self.names.configuration_path, &self.names.configuration_iface, method, build)
+    }
+
+    fn call_call<F>(&self, method: &str, build: F) -> Result<Message, RingError>
+        where F: FnOnce(Message) -> Message
+    {
+        self.call(&self.names.call_path, &self.names.call_iface, method, build)
+    }
+
+    /**
+     * Read the list of details returned by getContacts and getTrustRequests
+     * @param response of the daemon
+     * @param key of the wanted detail
+     * @return the wanted detail of each entry
+     */
+    fn read_details_list(response: &Message, key: &str) -> Result<Vec<String>, RingError> {
+        let mut result: Vec<String> = Vec::new();
+        let details_vec: Array<Dict<&str, &str, _>, _> = response.read1()?;
+        for details in details_vec {
+            for (k, value) in details {
+                if k == key {
+                    result.push(value.to_string());
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl RingDaemon for DBusDaemon {
+    fn get_account_list(&self) -> Result<Vec<String>, RingError> {
+        let response = self.call_configuration("getAccountList", |msg| msg)?;
+        // getAccountList returns one argument, which is an array of strings.
+        let accounts: Array<&str, _> = response.read1()?;
+        Ok(accounts.map(|account| account.to_string()).collect())
+    }
+
+    fn get_account_details(&self, account_id: &str) -> Result<HashMap<String, String>, RingError> {
+        let response = self.call_configuration("getAccountDetails", |msg| msg.append1(account_id))?;
+        let details: Dict<&str, &str, _> = response.read1()?;
+        Ok(details.map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+
+    fn get_volatile_account_details(&self, account_id: &str) -> Result<HashMap<String, String>, RingError> {
+        let response = self.call_configuration("getVolatileAccountDetails", |msg| msg.append1(account_id))?;
+        let details: Dict<&str, &str, _> = response.read1()?;
+        Ok(details.map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+
+    fn set_account_details(&self, account_id: &str, details: HashMap<String, String>) -> Result<(), RingError> {
+        let details = Dict::new(details.iter());
+        self.call_configuration("setAccountDetails", |msg| msg.append2(account_id, details))?;
+        Ok(())
+    }
+
+    fn add_account(&self, details: HashMap<String, String>) -> Result<String, RingError> {
+        let details = Dict::new(details.iter());
+        let response = self.call_configuration("addAccount", |msg| msg.append1(details))?;
+        // addAccount returns one argument, which is a string.
+        let account_added: String = response.read1()?;
+        Ok(account_added)
+    }
+
+    fn rm_account(&self, account_id: &str) -> Result<(), RingError> {
+        self.call_configuration("removeAccount", |msg| msg.append1(account_id))?;
+        Ok(())
+    }
+
+    fn enable_account(&self, account_id: &str, enable: bool) -> Result<(), RingError> {
+        self.call_configuration("sendRegister", |msg| msg.append2(account_id, enable))?;
+        Ok(())
+    }
+
+    fn export_to_file(&self, account_id: &str, path: &str, password: &str) -> Result<bool, RingError> {
+        let response = self.call_configuration("exportToFile", |msg| msg.append3(account_id, path, password))?;
+        Ok(response.read1()?)
+    }
+
+    fn change_account_password(&self, account_id: &str, old_password: &str, new_password: &str) -> Result<bool, RingError> {
+        let response = self.call_configuration("changeAccountPassword",
+                                               |msg| msg.append3(account_id, old_password, new_password))?;
+        Ok(response.read1()?)
+    }
+
+    fn get_contacts(&self, account_id: &str) -> Result<Vec<String>, RingError> {
+        let response = self.call_configuration("getContacts", |msg| msg.append1(account_id))?;
+        DBusDaemon::read_details_list(&response, "id")
+    }
+
+    fn add_contact(&self, account_id: &str, contact: &str) -> Result<(), RingError> {
+        self.call_configuration("addContact", |msg| msg.append2(account_id, contact))?;
+        Ok(())
+    }
+
+    fn rm_contact(&self, account_id: &str, contact: &str, banned: bool) -> Result<(), RingError> {
+        self.call_configuration("removeContact", |msg| msg.append3(account_id, contact, banned))?;
+        Ok(())
+    }
+
+    fn get_requests(&self, account_id: &str) -> Result<Vec<String>, RingError> {
+        let response = self.call_configuration("getTrustRequests", |msg| msg.append1(account_id))?;
+        DBusDaemon::read_details_list(&response, "from")
+    }
+
+    fn send_trust_request(&self, from: &str, destination: &str) -> Result<(), RingError> {
+        // TODO image
+        let buf = &[0x00u8];
+        let payloads = Array::new(buf.iter());
+
+        self.call_configuration("sendTrustRequest", |msg| msg.append3(from, destination, payloads))?;
+        Ok(())
+    }
+
+    fn accept_request(&self, account_id: &str, from: &str, accept: bool) -> Result<bool, RingError> {
+        let method = if accept {"acceptTrustRequest"} else {"discardTrustRequest"};
+        let response = self.call_configuration(method, |msg| msg.append2(account_id, from))?;
+        let result: bool = response.read1()?;
+        Ok(result)
+    }
+
+    fn send_text_message(&self, from: &str, destination: &str,
+                         payloads: HashMap<String, String>) -> Result<u64, RingError> {
+        let payloads = Dict::new(payloads.iter());
+        let response = self.call_configuration("sendTextMessage",
+                                               |msg| msg.append3(from, destination, payloads))?;
+        // sendTextMessage returns one argument, which is a u64.
+        let interaction_id: u64 = response.read1()?;
+        Ok(interaction_id)
+    }
+
+    fn lookup_name(&self, account_id: &str, name_server: &str, name: &str) -> Result<bool, RingError> {
+        let response = self.call_configuration("lookupName", |msg| msg.append3(account_id, name_server, name))?;
+        Ok(response.read1()?)
+    }
+
+    fn lookup_address(&self, account_id: &str, name_server: &str, address: &str) -> Result<bool, RingError> {
+        let response = self.call_configuration("lookupAddress", |msg| msg.append3(account_id, name_server, address))?;
+        Ok(response.read1()?)
+    }
+
+    fn register_name(&self, account_id: &str, password: &str, name: &str) -> Result<bool, RingError> {
+        let response = self.call_configuration("registerName", |msg| msg.append3(account_id, password, name))?;
+        Ok(response.read1()?)
+    }
+
+    fn get_known_ring_devices(&self, account_id: &str) -> Result<HashMap<String, String>, RingError> {
+        let response = self.call_configuration("getKnownRingDevices", |msg| msg.append1(account_id))?;
+        let devices: Dict<&str, &str, _> = response.read1()?;
+        Ok(devices.map(|(id, name)| (id.to_string(), name.to_string())).collect())
+    }
+
+    fn revoke_device(&self, account_id: &str, password: &str, device_id: &str) -> Result<bool, RingError> {
+        let response = self.call_configuration("revokeDevice", |msg| msg.append3(account_id, password, device_id))?;
+        Ok(response.read1()?)
+    }
+
+    fn export_on_ring(&self, account_id: &str, password: &str) -> Result<bool, RingError> {
+        let response = self.call_configuration("exportOnRing", |msg| msg.append2(account_id, password))?;
+        Ok(response.read1()?)
+    }
+
+    fn place_call(&self, account_id: &str, destination: &str) -> Result<String, RingError> {
+        let response = self.call_call("placeCall", |msg| msg.append2(account_id, destination))?;
+        let call_id: String = response.read1()?;
+        Ok(call_id)
+    }
+
+    fn accept(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("accept", |msg| msg.append1(call_id))?.read1()?)
+    }
+
+    fn refuse(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("refuse", |msg| msg.append1(call_id))?.read1()?)
+    }
+
+    fn hang_up(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("hangUp", |msg| msg.append1(call_id))?.read1()?)
+    }
+
+    fn hold(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("hold", |msg| msg.append1(call_id))?.read1()?)
+    }
+
+    fn unhold(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("unhold", |msg| msg.append1(call_id))?.read1()?)
+    }
+
+    fn mute_local_media(&self, call_id: &str, media_type: &str, mute: bool) -> Result<bool, RingError> {
+        Ok(self.call_call("muteLocalMedia", |msg| msg.append3(call_id, media_type, mute))?.read1()?)
+    }
+
+    fn transfer(&self, call_id: &str, to: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("transfer", |msg| msg.append2(call_id, to))?.read1()?)
+    }
+
+    fn play_dtmf(&self, key: &str) -> Result<(), RingError> {
+        self.call_call("playDTMF", |msg| msg.append1(key))?;
+        Ok(())
+    }
+
+    fn get_call_list(&self) -> Result<Vec<String>, RingError> {
+        let response = self.call_call("getCallList", |msg| msg)?;
+        let calls: Array<&str, _> = response.read1()?;
+        Ok(calls.map(|call| call.to_string()).collect())
+    }
+
+    fn get_call_details(&self, call_id: &str) -> Result<HashMap<String, String>, RingError> {
+        let response = self.call_call("getCallDetails", |msg| msg.append1(call_id))?;
+        let details: Dict<&str, &str, _> = response.read1()?;
+        Ok(details.map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+
+    fn join_participant(&self, sel_call_id: &str, drag_call_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("joinParticipant", |msg| msg.append2(sel_call_id, drag_call_id))?.read1()?)
+    }
+
+    fn create_conf_from_participant_list(&self, participants: Vec<String>) -> Result<(), RingError> {
+        self.call_call("createConfFromParticipantList", |msg| msg.append1(participants))?;
+        Ok(())
+    }
+
+    fn add_participant(&self, call_id: &str, conference_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("addParticipant", |msg| msg.append2(call_id, conference_id))?.read1()?)
+    }
+
+    fn detach_participant(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("detachParticipant", |msg| msg.append1(call_id))?.read1()?)
+    }
+
+    fn hang_up_conference(&self, conference_id: &str) -> Result<bool, RingError> {
+        Ok(self.call_call("hangUpConference", |msg| msg.append1(conference_id))?.read1()?)
+    }
+
+    fn get_conference_list(&self) -> Result<Vec<String>, RingError> {
+        let response = self.call_call("getConferenceList", |msg| msg)?;
+        let conferences: Array<&str, _> = response.read1()?;
+        Ok(conferences.map(|conference| conference.to_string()).collect())
+    }
+
+    fn get_participant_list(&self, conference_id: &str) -> Result<Vec<String>, RingError> {
+        let response = self.call_call("getParticipantList", |msg| msg.append1(conference_id))?;
+        let participants: Array<&str, _> = response.read1()?;
+        Ok(participants.map(|participant| participant.to_string()).collect())
+    }
+
+    fn get_conference_details(&self, conference_id: &str) -> Result<HashMap<String, String>, RingError> {
+        let response = self.call_call("getConferenceDetails", |msg| msg.append1(conference_id))?;
+        let details: Dict<&str, &str, _> = response.read1()?;
+        Ok(details.map(|(key, value)| (key.to_string(), value.to_string())).collect())
+    }
+
+    fn data_transfer_list(&self) -> Result<Vec<u64>, RingError> {
+        let response = self.call_configuration("dataTransferList", |msg| msg)?;
+        let ids: Array<u64, _> = response.read1()?;
+        Ok(ids.collect())
+    }
+
+    fn send_file(&self, info: DataTransferInfo) -> Result<(u32, u64), RingError> {
+        Ok(self.call_configuration("sendFile", |msg| msg.append1(info))?.read2()?)
+    }
+
+    fn data_transfer_info(&self, id: u64) -> Result<(u32, DataTransferInfo), RingError> {
+        Ok(self.call_configuration("dataTransferInfo", |msg| msg.append1(id))?.read2()?)
+    }
+
+    fn data_transfer_bytes_progress(&self, id: u64) -> Result<(u32, i64, i64), RingError> {
+        Ok(self.call_configuration("dataTransferBytesProgress", |msg| msg.append1(id))?.read3()?)
+    }
+
+    fn accept_file_transfer(&self, id: u64, file_path: &str, offset: i64) -> Result<u32, RingError> {
+        Ok(self.call_configuration("acceptFileTransfer", |msg| msg.append3(id, file_path, offset))?.read1()?)
+    }
+
+    fn cancel_data_transfer(&self, id: u64) -> Result<u32, RingError> {
+        Ok(self.call_configuration("cancelDataTransfer", |msg| msg.append1(id))?.read1()?)
+    }
+
+    fn listener(&self) -> Result<Option<Box<dyn EventSource>>, RingError> {
+        // Use another dbus connection to listen signals.
+        let listener = SignalListener::new(self.bus.open()?, self.names.clone())?;
+        Ok(Some(Box::new(listener)))
+    }
+}
@@ -0,0 +1,103 @@
+use dbus;
+use dbus::arg::TypeMismatchError;
+use std::error::Error;
+use std::fmt;
+
+/**
+ * Everything that can go wrong when talking to the Ring daemon.
+ * Used so clients can tell an empty answer from a failing daemon.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum RingError {
+    // The session bus or the daemon can't be reached
+    DaemonUnreachable(String),
+    // The daemon doesn't know the method (or the interface)
+    MethodMissing(String),
+    // The daemon didn't answer in time
+    Timeout(String),
+    // The reply doesn't have the expected signature
+    BadReply(String),
+    // The daemon answered with an error
+    Daemon { name: String, message: String },
+    // The name server doesn't know this name
+    NameNotFound(String),
+    // The daemon doesn't know this call
+    UnknownCall(String),
+    // The daemon doesn't know this conference
+    UnknownConference(String),
+    // What the user asked to call can't be called from this account
+    InvalidDestination { destination: String, reason: String },
+    // The daemon doesn't know this file transfer
+    UnknownTransfer(u64),
+    // The daemon can't read or write the file of a transfer
+    TransferFailed(String),
+    // The settings of a new account are incomplete
+    InvalidAccount(String),
+}
+
+impl fmt::Display for RingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RingError::DaemonUnreachable(ref what) => write!(f, "daemon unreachable: {}", what),
+            RingError::MethodMissing(ref method) => write!(f, "method missing: {}", method),
+            RingError::Timeout(ref method) => write!(f, "timeout: {}", method),
+            RingError::BadReply(ref what) => write!(f, "bad reply: {}", what),
+            RingError::Daemon { ref name, ref message } => write!(f, "{}: {}", name, message),
+            RingError::NameNotFound(ref name) => write!(f, "name not found: {}", name),
+            RingError::UnknownCall(ref call_id) => write!(f, "unknown call: {}", call_id),
+            RingError::UnknownConference(ref conference_id) => write!(f, "unknown conference: {}", conference_id),
+            RingError::InvalidDestination { ref destination, ref reason } =>
+                write!(f, "invalid destination '{}': {}", destination, reason),
+            RingError::UnknownTransfer(id) => write!(f, "unknown transfer: {}", id),
+            RingError::TransferFailed(ref what) => write!(f, "transfer failed: {}", what),
+            RingError::InvalidAccount(ref reason) => write!(f, "invalid account: {}", reason),
+        }
+    }
+}
+
+impl Error for RingError {
+    fn description(&self) -> &str {
+        match *self {
+            RingError::DaemonUnreachable(_) => "daemon unreachable",
+            RingError::MethodMissing(_) => "method missing",
+            RingError::Timeout(_) => "timeout",
+            RingError::BadReply(_) => "bad reply",
+            RingError::Daemon { .. } => "daemon error",
+            RingError::NameNotFound(_) => "name not found",
+            RingError::UnknownCall(_) => "unknown call",
+            RingError::UnknownConference(_) => "unknown conference",
+            RingError::InvalidDestination { .. } => "invalid destination",
+            RingError::UnknownTransfer(_) => "unknown transfer",
+            RingError::TransferFailed(_) => "transfer failed",
+            RingError::InvalidAccount(_) => "invalid account",
+        }
+    }
+}
+
+impl From<dbus::Error> for RingError {
+    fn from(err: dbus::Error) -> RingError {
+        let name = err.name().unwrap_or("").to_string();
+        let message = err.message().unwrap_or("").to_string();
+        match &*name {
+            "org.freedesktop.DBus.Error.ServiceUnknown"
+            | "org.freedesktop.DBus.Error.NameHasNoOwner"
+            | "org.freedesktop.DBus.Error.NoServer"
+            | "org.freedesktop.DBus.Error.NoNetwork"
+            | "org.freedesktop.DBus.Error.Disconnected"
+            | "org.freedesktop.DBus.Error.FileNotFound" => RingError::DaemonUnreachable(message),
+            "org.freedesktop.DBus.Error.UnknownMethod"
+            | "org.freedesktop.DBus.Error.UnknownInterface"
+            | "org.freedesktop.DBus.Error.UnknownObject" => RingError::MethodMissing(message),
+            "org.freedesktop.DBus.Error.NoReply"
+            | "org.freedesktop.DBus.Error.Timeout"
+            | "org.freedesktop.DBus.Error.TimedOut" => RingError::Timeout(message),
+            _ => RingError::Daemon { name, message },
+        }
+    }
+}
+
+impl From<TypeMismatchError> for RingError {
+    fn from(err: TypeMismatchError) -> RingError {
+        RingError::BadReply(format!("{}", err))
+    }
+}
@@ -0,0 +1,947 @@
+use ring::api::interaction::Direction;
+use ring::api::transfer::{DataTransferInfo, Transfer, TransferStatus, TRANSFER_INVALID_ARGUMENT,
+                          TRANSFER_SUCCESS, TRANSFER_UNKNOWN};
+use ring::dbus_daemon::DaemonNames;
+use ring::manager::Manager;
+
+use dbus::{Connection, ConnectionItem, Message, NameFlag};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::thread::JoinHandle;
+
+/**
+ * What the fake daemon knows. Tests can read and modify it at any time
+ * through FakeDaemon::state()
+ */
+pub struct FakeState {
+    // (accountId, details), in creation order
+    pub accounts: Vec<(String, HashMap<String, String>)>,
+    // accountId -> contacts
+    pub contacts: HashMap<String, Vec<String>>,
+    // accountId -> pending trust requests
+    pub requests: HashMap<String, Vec<String>>,
+    // (accountId, destination, payloads) for each sendTextMessage
+    pub sent_messages: Vec<(String, String, HashMap<String, String>)>,
+    // (accountId, destination) for each sendTrustRequest
+    pub sent_requests: Vec<(String, String)>,
+    // (accountId, destination) for each placeCall
+    pub placed_calls: Vec<(String, String)>,
+    // (callId, details) of the current calls
+    pub calls: Vec<(String, HashMap<String, String>)>,
+    // (conferenceId, callIds) of the current conferences
+    pub conferences: Vec<(String, Vec<String>)>,
+    // (callId, destination) for each transfer
+    pub transfers: Vec<(String, String)>,
+    // tones played, in order
+    pub dtmf: String,
+    // file transfers, sent and received
+    pub data_transfers: Vec<Transfer>,
+    // registered name -> ring id, for the name server
+    pub names: HashMap<String, String>,
+    // accountId -> (deviceId -> name) of the linked devices
+    pub devices: HashMap<String, HashMap<String, String>>,
+    // accountId -> archive password, empty if not set
+    pub passwords: HashMap<String, String>,
+    // method -> (error name, error message) to reply instead of the result
+    pub failures: HashMap<String, (String, String)>,
+    next_id: u64,
+    // Signals to send after the current reply
+    signals: Vec<FakeSignal>,
+}
+
+impl FakeState {
+    fn new() -> FakeState {
+        FakeState {
+            accounts: Vec::new(),
+            contacts: HashMap::new(),
+            requests: HashMap::new(),
+            sent_messages: Vec::new(),
+            sent_requests: Vec::new(),
+            placed_calls: Vec::new(),
+            calls: Vec::new(),
+            conferences: Vec::new(),
+            transfers: Vec::new(),
+            dtmf: String::new(),
+            data_transfers: Vec::new(),
+            names: HashMap::new(),
+            devices: HashMap::new(),
+            passwords: HashMap::new(),
+            failures: HashMap::new(),
+            next_id: 1,
+            signals: Vec::new(),
+        }
+    }
+
+    /**
+     * Add a RING account to the daemon
+     * @param self
+     * @param alias
+     * @param username the ring id of the account
+     * @return the new account id
+     */
+    pub fn add_account(&mut self, alias: &str, username: &str) -> String {
+        let mut details = HashMap::new();
+        details.insert(String::from("Account.type"), String::from("RING"));
+        details.insert(String::from("Account.enable"), String::from("true"));
+        details.insert(String::from("Account.alias"), alias.to_string());
+        details.insert(String::from("Account.username"), username.to_string());
+        self.insert_account(details)
+    }
+
+    /**
+     * Add a SIP account to the daemon
+     * @param self
+     * @param alias
+     * @param username
+     * @param hostname of the registrar
+     * @return the new account id
+     */
+    pub fn add_sip_account(&mut self, alias: &str, username: &str, hostname: &str) -> String {
+        let mut details = HashMap::new();
+        details.insert(String::from("Account.type"), String::from("SIP"));
+        details.insert(String::from("Account.enable"), String::from("true"));
+        details.insert(String::from("Account.alias"), alias.to_string());
+        details.insert(String::from("Account.username"), username.to_string());
+        details.insert(String::from("Account.hostname"), hostname.to_string());
+        self.insert_account(details)
+    }
+
+    /**
+     * Make the next calls to a method fail with a daemon error
+     * @param self
+     * @param method
+     * @param name of the error
+     * @param message of the error
+     */
+    pub fn fail(&mut self, method: &str, name: &str, message: &str) {
+        self.failures.insert(method.to_string(), (name.to_string(), message.to_string()));
+    }
+
+    fn insert_account(&mut self, details: HashMap<String, String>) -> String {
+        let id = format!("{:016x}", self.next_id);
+        self.next_id += 1;
+        self.accounts.push((id.clone(), details));
+        id
+    }
+
+    /**
+     * Change the state of a call, forgetting it once over
+     * @return false if the call is unknown
+     */
+    fn set_call_state(&mut self, call_id: &str, state: &str) -> bool {
+        let found = match self.calls.iter_mut().find(|c| c.0 == call_id) {
+            Some(call) => {
+                call.1.insert(String::from("CALL_STATE"), state.to_string());
+                true
+            },
+            None => false,
+        };
+        if state == "OVER" {
+            self.calls.retain(|c| c.0 != call_id);
+        }
+        found
+    }
+
+    /**
+     * Change the state of a call and tell it to clients
+     * @return false if the call is unknown
+     */
+    fn change_call_state(&mut self, call_id: &str, state: &str) -> bool {
+        let found = self.set_call_state(call_id, state);
+        if found {
+            self.signals.push(FakeSignal::CallStateChanged(call_id.to_string(), state.to_string(), 0));
+        }
+        if state == "OVER" {
+            self.detach(call_id);
+        }
+        found
+    }
+
+    /**
+     * Take a call out of its conference, removing conferences left with one call
+     * @return false if the call wasn't in a conference
+     */
+    fn detach(&mut self, call_id: &str) -> bool {
+        let mut found = false;
+        let mut signals = Vec::new();
+        for conference in self.conferences.iter_mut() {
+            let count = conference.1.len();
+            conference.1.retain(|c| c != call_id);
+            if count != conference.1.len() {
+                found = true;
+                signals.push(if conference.1.len() > 1 {
+                    FakeSignal::ConferenceChanged(conference.0.clone(), String::from("ACTIVE_ATTACHED"))
+                } else {
+                    FakeSignal::ConferenceRemoved(conference.0.clone())
+                });
+            }
+        }
+        self.conferences.retain(|c| c.1.len() > 1);
+        self.signals.extend(signals);
+        found
+    }
+
+    /**
+     * Add a call to a conference and tell it to clients
+     * @return false if the call or the conference is unknown
+     */
+    fn join(&mut self, call_id: &str, conference_id: &str) -> bool {
+        if !self.calls.iter().any(|c| c.0 == call_id) || !self.conferences.iter().any(|c| c.0 == conference_id) {
+            return false;
+        }
+        self.detach(call_id);
+        match self.conferences.iter_mut().find(|c| c.0 == conference_id) {
+            Some(conference) => conference.1.push(call_id.to_string()),
+            None => return false,
+        }
+        self.signals.push(FakeSignal::ConferenceChanged(conference_id.to_string(), String::from("ACTIVE_ATTACHED")));
+        true
+    }
+
+    /**
+     * Change the status of a file transfer and tell it to clients
+     * @return false if the transfer is unknown
+     */
+    fn change_transfer_status(&mut self, id: u64, status: TransferStatus) -> bool {
+        match self.data_transfers.iter_mut().find(|t| t.id == id) {
+            Some(transfer) => transfer.status = status,
+            None => return false,
+        }
+        self.signals.push(FakeSignal::DataTransferEvent(id, status.to_daemon() as i32));
+        true
+    }
+
+    fn account_mut(&mut self, id: &str) -> Option<&mut HashMap<String, String>> {
+        self.accounts.iter_mut().find(|a| a.0 == id).map(|a| &mut a.1)
+    }
+}
+
+/**
+ * Signals the fake daemon can emit
+ */
+enum FakeSignal {
+    IncomingAccountMessage(String, String, HashMap<String, String>),
+    IncomingTrustRequest(String, String),
+    AccountsChanged,
+    RegistrationStateChanged(String, String, i32, String),
+    AccountMessageStatusChanged(String, u64, String, i32),
+    RegisteredNameFound(String, i32, String, String),
+    IncomingCall(String, String, String),
+    CallStateChanged(String, String, i32),
+    ConferenceCreated(String),
+    ConferenceChanged(String, String),
+    ConferenceRemoved(String),
+    DataTransferEvent(u64, i32),
+    NameRegistrationEnded(String, i32, String),
+    KnownDevicesChanged(String, HashMap<String, String>),
+    DeviceRevocationEnded(String, String, i32),
+    ExportOnRingEnded(String, i32, String),
+}
+
+impl FakeSignal {
+    fn into_message(self, names: &DaemonNames) -> Message {
+        let signal = |member: &str| Message::new_signal(&*names.configuration_path,
+                                                        &*names.configuration_iface, member).unwrap();
+        let call_signal = |member: &str| Message::new_signal(&*names.call_path, &*names.call_iface, member).unwrap();
+        match self {
+            FakeSignal::IncomingAccountMessage(account_id, from, payloads) =>
+                signal("incomingAccountMessage").append3(account_id, from, payloads),
+            FakeSignal::IncomingTrustRequest(account_id, from) =>
+                signal("incomingTrustRequest").append3(account_id, from, Vec::<u8>::new()).append1(0u64),
+            FakeSignal::AccountsChanged => signal("accountsChanged"),
+            FakeSignal::RegistrationStateChanged(account_id, state, code, detail) =>
+                signal("registrationStateChanged").append3(account_id, state, code).append1(detail),
+            FakeSignal::AccountMessageStatusChanged(account_id, message_id, peer, status) =>
+                signal("accountMessageStatusChanged").append3(account_id, message_id, peer).append1(status),
+            FakeSignal::RegisteredNameFound(account_id, status, address, name) =>
+                signal("registeredNameFound").append3(account_id, status, address).append1(name),
+            FakeSignal::KnownDevicesChanged(account_id, devices) =>
+                signal("knownDevicesChanged").append2(account_id, devices),
+            FakeSignal::DeviceRevocationEnded(account_id, device_id, status) =>
+                signal("deviceRevocationEnded").append3(account_id, device_id, status),
+            FakeSignal::ExportOnRingEnded(account_id, status, pin) =>
+                signal("exportOnRingEnded").append3(account_id, status, pin),
+            FakeSignal::DataTransferEvent(id, code) =>
+                signal("dataTransferEvent").append2(id, code),
+            FakeSignal::NameRegistrationEnded(account_id, state, name) =>
+                signal("nameRegistrationEnded").append3(account_id, state, name),
+            FakeSignal::IncomingCall(account_id, call_id, from) =>
+                call_signal("incomingCall").append3(account_id, call_id, from),
+            FakeSignal::CallStateChanged(call_id, state, code) =>
+                call_signal("callStateChanged").append3(call_id, state, code),
+            FakeSignal::ConferenceCreated(conference_id) =>
+                call_signal("conferenceCreated").append1(conference_id),
+            FakeSignal::ConferenceChanged(conference_id, state) =>
+                call_signal("conferenceChanged").append2(conference_id, state),
+            FakeSignal::ConferenceRemoved(conference_id) =>
+                call_signal("conferenceRemoved").append1(conference_id),
+        }
+    }
+}
+
+/**
+ * A Ring daemon living on a private bus, for the tests.
+ * The bus (a `dbus-daemon --session`) and the daemon are stopped on drop.
+ */
+pub struct FakeDaemon {
+    address: String,
+    state: Arc<Mutex<FakeState>>,
+    signals: Sender<FakeSignal>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    bus: Child,
+}
+
+impl FakeDaemon {
+    /**
+     * Launch a private bus and register a Ring daemon on it
+     * @return the running daemon
+     */
+    pub fn start() -> FakeDaemon {
+        FakeDaemon::start_with_names(DaemonNames::ring())
+    }
+
+    /**
+     * Launch a private bus and register the daemon on it
+     * @param names used by the daemon on the bus
+     * @return the running daemon
+     */
+    pub fn start_with_names(names: DaemonNames) -> FakeDaemon {
+        let mut bus = Command::new("dbus-daemon")
+                              .args(["--session", "--nofork", "--print-address"])
+                              .stdout(Stdio::piped())
+                              .spawn()
+                              .expect("Can't launch dbus-daemon");
+        let mut address = String::new();
+        BufReader::new(bus.stdout.take().unwrap()).read_line(&mut address).unwrap();
+        let address = address.trim().to_string();
+
+        let state = Arc::new(Mutex::new(FakeState::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (signals, pending_signals) = channel::<FakeSignal>();
+        let (ready_tx, ready_rx) = channel::<()>();
+
+        let thread_address = address.clone();
+        let thread_state = state.clone();
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || {
+            let conn = Connection::open_private(&thread_address).unwrap();
+            conn.register().unwrap();
+            conn.register_name(&names.service, NameFlag::DoNotQueue as u32).unwrap();
+            conn.register_object_path(&names.configuration_path).unwrap();
+            conn.register_object_path(&names.call_path).unwrap();
+            ready_tx.send(()).unwrap();
+            while !thread_stop.load(Ordering::SeqCst) {
+                for signal in pending_signals.try_iter() {
+                    let _ = conn.send(signal.into_message(&names));
+                }
+                for item in conn.iter(10) {
+                    match item {
+                        ConnectionItem::MethodCall(call) => {
+                            let reply = handle_method_call(&thread_state, &names, &call);
+                            let _ = conn.send(reply);
+                            let signals: Vec<FakeSignal> = thread_state.lock().unwrap().signals.drain(..).collect();
+                            for signal in signals {
+                                let _ = conn.send(signal.into_message(&names));
+                            }
+                        },
+                        ConnectionItem::Nothing => break,
+                        _ => {}
+                    }
+                }
+            }
+        });
+        ready_rx.recv().unwrap();
+
+        FakeDaemon {
+            address,
+            state,
+            signals,
+            stop,
+            thread: Some(thread),
+            bus,
+        }
+    }
+
+    /**
+     * @return a Manager connected to this daemon
+     */
+    pub fn manager(&self) -> Manager {
+        Manager::with_address(&self.address).unwrap()
+    }
+
+    /**
+     * Access the scriptable state of the daemon
+     */
+    pub fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn emit_incoming_message(&self, account_id: &str, from: &str, payloads: HashMap<String, String>) {
+        self.emit(FakeSignal::IncomingAccountMessage(account_id.to_string(), from.to_string(), payloads));
+    }
+
+    pub fn emit_trust_request(&self, account_id: &str, from: &str) {
+        self.state().requests.entry(account_id.to_string()).or_default().push(from.to_string());
+        self.emit(FakeSignal::IncomingTrustRequest(account_id.to_string(), from.to_string()));
+    }
+
+    pub fn emit_accounts_changed(&self) {
+        self.emit(FakeSignal::AccountsChanged);
+    }
+
+    pub fn emit_registration_state(&self, account_id: &str, state: &str, code: i32, detail: &str) {
+        self.emit(FakeSignal::RegistrationStateChanged(account_id.to_string(), state.to_string(),
+                                                       code, detail.to_string()));
+    }
+
+    pub fn emit_message_status(&self, account_id: &str, message_id: u64, peer: &str, status: i32) {
+        self.emit(FakeSignal::AccountMessageStatusChanged(account_id.to_string(), message_id,
+                                                          peer.to_string(), status));
+    }
+
+    /**
+     * Make someone call an account
+     * @param self
+     * @param account_id
+     * @param from uri of the caller
+     * @return the id of the new call
+     */
+    pub fn emit_incoming_call(&self, account_id: &str, from: &str) -> String {
+        let call_id = {
+            let mut state = self.state();
+            let call_id = format!("{}", state.next_id);
+            state.next_id += 1;
+            let mut details = HashMap::new();
+            details.insert(String::from("ACCOUNTID"), account_id.to_string());
+            details.insert(String::from("PEER_NUMBER"), from.to_string());
+            details.insert(String::from("CALL_TYPE"), String::from("0"));
+            details.insert(String::from("CALL_STATE"), String::from("INCOMING"));
+            state.calls.push((call_id.clone(), details));
+            call_id
+        };
+        self.emit(FakeSignal::IncomingCall(account_id.to_string(), call_id.clone(), from.to_string()));
+        call_id
+    }
+
+    /**
+     * Change the state of a call, as if the peer did something
+     * @param self
+     * @param call_id
+     * @param state e.g. RINGING, CURRENT, HUNGUP
+     */
+    pub fn emit_call_state(&self, call_id: &str, state: &str) {
+        self.state().set_call_state(call_id, state);
+        self.emit(FakeSignal::CallStateChanged(call_id.to_string(), state.to_string(), 0));
+    }
+
+    /**
+     * Make someone offer a file to an account
+     * @param self
+     * @param account_id
+     * @param from ring id of the sender
+     * @param display_name name of the file
+     * @param size of the file
+     * @return the id of the new transfer
+     */
+    pub fn emit_incoming_transfer(&self, account_id: &str, from: &str, display_name: &str, size: i64) -> u64 {
+        let id = {
+            let mut state = self.state();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.data_transfers.push(Transfer {
+                id,
+                account_id: account_id.to_string(),
+                peer: from.to_string(),
+                direction: Direction::Incoming,
+                status: TransferStatus::WaitHostAcceptance,
+                display_name: display_name.to_string(),
+                path: String::new(),
+                mime_type: String::from("application/octet-stream"),
+                total_size: size,
+                progress: 0,
+            });
+            id
+        };
+        self.emit(FakeSignal::DataTransferEvent(id, TransferStatus::WaitHostAcceptance.to_daemon() as i32));
+        id
+    }
+
+    /**
+     * Move a transfer forward, finishing it once every byte is there
+     * @param self
+     * @param id of the transfer
+     * @param progress bytes transferred
+     */
+    pub fn emit_transfer_progress(&self, id: u64, progress: i64) {
+        let status = {
+            let mut state = self.state();
+            match state.data_transfers.iter_mut().find(|t| t.id == id) {
+                Some(transfer) => {
+                    transfer.progress = progress;
+                    transfer.status = if progress >= transfer.total_size {TransferStatus::Finished} else {TransferStatus::Ongoing};
+                    transfer.status
+                },
+                None => return,
+            }
+        };
+        self.emit(FakeSignal::DataTransferEvent(id, status.to_daemon() as i32));
+    }
+
+    fn emit(&self, signal: FakeSignal) {
+        self.signals.send(signal).unwrap();
+    }
+}
+
+impl Drop for FakeDaemon {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let _ = self.bus.kill();
+        let _ = self.bus.wait();
+    }
+}
+
+/**
+ * Answer a method call from the state
+ * @param state of the daemon
+ * @param names used by the daemon on the bus
+ * @param call the method call
+ * @return the reply to send
+ */
+fn handle_method_call(state: &Arc<Mutex<FakeState>>, names: &DaemonNames, call: &Message) -> Message {
+    let mut state = state.lock().unwrap();
+    let iface = call.interface().map(|i| i.to_string()).unwrap_or_default();
+    let member = call.member().map(|m| m.to_string()).unwrap_or_default();
+    if let Some((name, message)) = state.failures.get(&member) {
+        return Message::new_error(call, name, message).unwrap();
+    }
+    let reply = Message::new_method_return(call).unwrap();
+    if iface == names.call_iface {
+        return match &*member {
+            "placeCall" => {
+                let (account_id, destination) = call.get2::<String, String>();
+                let (account_id, destination) = (account_id.unwrap_or(String::new()), destination.unwrap_or(String::new()));
+                state.placed_calls.push((account_id.clone(), destination.clone()));
+                let call_id = format!("{}", state.next_id);
+                state.next_id += 1;
+                let mut details = HashMap::new();
+                details.insert(String::from("ACCOUNTID"), account_id);
+                details.insert(String::from("PEER_NUMBER"), destination);
+                details.insert(String::from("CALL_TYPE"), String::from("1"));
+                details.insert(String::from("CALL_STATE"), String::from("CONNECTING"));
+                state.calls.push((call_id.clone(), details));
+                reply.append1(call_id)
+            },
+            "accept" | "unhold" => {
+                let call_id: String = call.get1().unwrap_or_default();
+                reply.append1(state.change_call_state(&call_id, "CURRENT"))
+            },
+            "hold" => {
+                let call_id: String = call.get1().unwrap_or_default();
+                reply.append1(state.change_call_state(&call_id, "HOLD"))
+            },
+            "refuse" | "hangUp" => {
+                let call_id: String = call.get1().unwrap_or_default();
+                let found = state.change_call_state(&call_id, "HUNGUP");
+                state.change_call_state(&call_id, "OVER");
+                reply.append1(found)
+            },
+            "muteLocalMedia" => {
+                let (call_id, media_type, mute) = call.get3::<String, String, bool>();
+                let key = format!("{}_MUTED", media_type.unwrap_or(String::new()));
+                let found = match state.calls.iter_mut().find(|c| Some(&c.0) == call_id.as_ref()) {
+                    Some(call) => {
+                        call.1.insert(key, format!("{}", mute.unwrap_or(false)));
+                        true
+                    },
+                    None => false,
+                };
+                reply.append1(found)
+            },
+            "transfer" => {
+                let (call_id, to) = call.get2::<String, String>();
+                let call_id = call_id.unwrap_or(String::new());
+                state.transfers.push((call_id.clone(), to.unwrap_or(String::new())));
+                let found = state.change_call_state(&call_id, "OVER");
+                reply.append1(found)
+            },
+            "playDTMF" => {
+                let key: String = call.get1().unwrap_or_default();
+                state.dtmf += &*key;
+                reply
+            },
+            "getCallList" => {
+                let ids: Vec<String> = state.calls.iter().map(|c| c.0.clone()).collect();
+                reply.append1(ids)
+            },
+            "getCallDetails" => {
+                let call_id: String = call.get1().unwrap_or_default();
+                let details = state.calls.iter().find(|c| c.0 == call_id).map(|c| c.1.clone()).unwrap_or(HashMap::new());
+                reply.append1(details)
+            },
+            "joinParticipant" => {
+                let (sel_call_id, drag_call_id) = call.get2::<String, String>();
+                let (sel_call_id, drag_call_id) = (sel_call_id.unwrap_or(String::new()), drag_call_id.unwrap_or(String::new()));
+                if sel_call_id == drag_call_id || !state.calls.iter().any(|c| c.0 == sel_call_id)
+                        || !state.calls.iter().any(|c| c.0 == drag_call_id) {
+                    return reply.append1(false);
+                }
+                let conference_id = state.conferences.iter().find(|c| c.1.contains(&sel_call_id)).map(|c| c.0.clone());
+                let joined = match conference_id {
+                    Some(conference_id) => state.join(&drag_call_id, &conference_id),
+                    None => {
+                        state.detach(&drag_call_id);
+                        let conference_id = format!("{}", state.next_id);
+                        state.next_id += 1;
+                        state.conferences.push((conference_id.clone(), vec![sel_call_id, drag_call_id]));
+                        state.signals.push(FakeSignal::ConferenceCreated(conference_id));
+                        true
+                    },
+                };
+                reply.append1(joined)
+            },
+            "createConfFromParticipantList" => {
+                let participants: Vec<String> = call.get1().unwrap_or_default();
+                let account_id = state.accounts.first().map(|a| a.0.clone()).unwrap_or_default();
+                let mut call_ids = Vec::new();
+                for participant in participants {
+                    let call_id = format!("{}", state.next_id);
+                    state.next_id += 1;
+                    let mut details = HashMap::new();
+                    details.insert(String::from("ACCOUNTID"), account_id.clone());
+                    details.insert(String::from("PEER_NUMBER"), participant);
+                    details.insert(String::from("CALL_TYPE"), String::from("1"));
+                    details.insert(String::from("CALL_STATE"), String::from("CONNECTING"));
+                    state.calls.push((call_id.clone(), details));
+                    call_ids.push(call_id);
+                }
+                let conference_id = format!("{}", state.next_id);
+                state.next_id += 1;
+                state.conferences.push((conference_id.clone(), call_ids));
+                state.signals.push(FakeSignal::ConferenceCreated(conference_id));
+                reply
+            },
+            "addParticipant" => {
+                let (call_id, conference_id) = call.get2::<String, String>();
+                let joined = state.join(&call_id.unwrap_or(String::new()), &conference_id.unwrap_or(String::new()));
+                reply.append1(joined)
+            },
+            "detachParticipant" => {
+                let call_id: String = call.get1().unwrap_or_default();
+                reply.append1(state.detach(&call_id))
+            },
+            "hangUpConference" => {
+                let conference_id: String = call.get1().unwrap_or_default();
+                let participants = state.conferences.iter().find(|c| c.0 == conference_id).map(|c| c.1.clone());
+                let found = participants.is_some();
+                for call_id in participants.unwrap_or(Vec::new()) {
+                    state.change_call_state(&call_id, "HUNGUP");
+                    state.change_call_state(&call_id, "OVER");
+                }
+                reply.append1(found)
+            },
+            "getConferenceList" => {
+                let ids: Vec<String> = state.conferences.iter().map(|c| c.0.clone()).collect();
+                reply.append1(ids)
+            },
+            "getParticipantList" => {
+                let conference_id: String = call.get1().unwrap_or_default();
+                let participants = state.conferences.iter().find(|c| c.0 == conference_id).map(|c| c.1.clone()).unwrap_or(Vec::new());
+                reply.append1(participants)
+            },
+            "getConferenceDetails" => {
+                let conference_id: String = call.get1().unwrap_or_default();
+                let mut details = HashMap::new();
+                if state.conferences.iter().any(|c| c.0 == conference_id) {
+                    details.insert(String::from("CONFID"), conference_id);
+                    details.insert(String::from("CONF_STATE"), String::from("ACTIVE_ATTACHED"));
+                }
+                reply.append1(details)
+            },
+            _ => unknown_method(call, &member),
+        };
+    }
+    if iface != names.configuration_iface {
+        return unknown_method(call, &member);
+    }
+    match &*member {
+        "getAccountList" => {
+            let ids: Vec<String> = state.accounts.iter().map(|a| a.0.clone()).collect();
+            reply.append1(ids)
+        },
+        "getAccountDetails" => {
+            let id: String = call.get1().unwrap_or_default();
+            let details = state.account_mut(&id).map(|d| d.clone()).unwrap_or_default();
+            reply.append1(details)
+        },
+        "getVolatileAccountDetails" => {
+            let id: String = call.get1().unwrap_or_default();
+            let enabled = state.account_mut(&id).and_then(|d| d.get("Account.enable").cloned());
+            let status = if enabled == Some(String::from("true")) {"REGISTERED"} else {"UNREGISTERED"};
+            let mut details = HashMap::new();
+            details.insert(String::from("Account.registrationStatus"), status.to_string());
+            reply.append1(details)
+        },
+        "setAccountDetails" => {
+            let (id, details) = call.get2::<String, HashMap<String, String>>();
+            if let Some(account) = state.account_mut(&id.unwrap_or(String::new())) {
+                *account = details.unwrap_or(HashMap::new());
+            }
+            reply
+        },
+        "addAccount" => {
+            let details: HashMap<String, String> = call.get1().unwrap_or_default();
+            let id = state.insert_account(details);
+            reply.append1(id)
+        },
+        "removeAccount" => {
+            let id: String = call.get1().unwrap_or_default();
+            state.accounts.retain(|a| a.0 != id);
+            reply
+        },
+        "exportToFile" => {
+            let (account_id, path, password) = call.get3::<String, String, String>();
+            let account_id = account_id.unwrap_or(String::new());
+            let details = match state.account_mut(&account_id) {
+                Some(details) => details.clone(),
+                None => return reply.append1(false),
+            };
+            if password.unwrap_or(String::new()) != state.passwords.get(&account_id).cloned().unwrap_or(String::new()) {
+                return reply.append1(false);
+            }
+            // Not encrypted, unlike the real one
+            let written = fs::write(path.unwrap_or(String::new()), format!("{:?}", details)).is_ok();
+            reply.append1(written)
+        },
+        "changeAccountPassword" => {
+            let (account_id, old_password, new_password) = call.get3::<String, String, String>();
+            let account_id = account_id.unwrap_or(String::new());
+            if state.account_mut(&account_id).is_none()
+                || old_password.unwrap_or(String::new()) != state.passwords.get(&account_id).cloned().unwrap_or(String::new()) {
+                return reply.append1(false);
+            }
+            state.passwords.insert(account_id, new_password.unwrap_or(String::new()));
+            reply.append1(true)
+        },
+        "sendRegister" => {
+            let (id, enable) = call.get2::<String, bool>();
+            if let Some(details) = state.account_mut(&id.unwrap_or(String::new())) {
+                details.insert(String::from("Account.enable"), format!("{}", enable.unwrap_or(false)));
+            }
+            reply
+        },
+        "dataTransferList" => {
+            let ids: Vec<u64> = state.data_transfers.iter().map(|t| t.id).collect();
+            reply.append1(ids)
+        },
+        "sendFile" => {
+            let info: DataTransferInfo = match call.get1() {
+                Some(info) => info,
+                None => return reply.append2(TRANSFER_INVALID_ARGUMENT, 0u64),
+            };
+            let id = state.next_id;
+            state.next_id += 1;
+            let mut transfer = Transfer::from_info(id, info);
+            transfer.direction = Direction::Outgoing;
+            transfer.status = TransferStatus::Created;
+            state.data_transfers.push(transfer);
+            state.signals.push(FakeSignal::DataTransferEvent(id, TransferStatus::Created.to_daemon() as i32));
+            state.change_transfer_status(id, TransferStatus::WaitPeerAcceptance);
+            reply.append2(TRANSFER_SUCCESS, id)
+        },
+        "dataTransferInfo" => {
+            let id: u64 = call.get1().unwrap_or(0);
+            match state.data_transfers.iter().find(|t| t.id == id) {
+                Some(transfer) => reply.append2(TRANSFER_SUCCESS, transfer.to_info()),
+                None => reply.append2(TRANSFER_UNKNOWN, Transfer::empty_info()),
+            }
+        },
+        "dataTransferBytesProgress" => {
+            let id: u64 = call.get1().unwrap_or(0);
+            match state.data_transfers.iter().find(|t| t.id == id) {
+                Some(transfer) => reply.append3(TRANSFER_SUCCESS, transfer.total_size, transfer.progress),
+                None => reply.append3(TRANSFER_UNKNOWN, 0i64, 0i64),
+            }
+        },
+        "acceptFileTransfer" => {
+            let (id, path, _) = call.get3::<u64, String, i64>();
+            let (id, path) = (id.unwrap_or(0), path.unwrap_or(String::new()));
+            let waiting = state.data_transfers.iter()
+                .find(|t| t.id == id).map(|t| t.status == TransferStatus::WaitHostAcceptance);
+            let error = match waiting {
+                None => TRANSFER_UNKNOWN,
+                Some(false) => TRANSFER_INVALID_ARGUMENT,
+                Some(true) if path.is_empty() => {
+                    state.change_transfer_status(id, TransferStatus::InvalidPathname);
+                    TRANSFER_SUCCESS
+                },
+                Some(true) => {
+                    if let Some(transfer) = state.data_transfers.iter_mut().find(|t| t.id == id) {
+                        transfer.path = path;
+                    }
+                    state.change_transfer_status(id, TransferStatus::Ongoing);
+                    TRANSFER_SUCCESS
+                },
+            };
+            reply.append1(error)
+        },
+        "cancelDataTransfer" => {
+            let id: u64 = call.get1().unwrap_or(0);
+            let found = state.change_transfer_status(id, TransferStatus::ClosedByHost);
+            reply.append1(if found {TRANSFER_SUCCESS} else {TRANSFER_UNKNOWN})
+        },
+        "sendTextMessage" => {
+            let (account_id, destination, payloads) = call.get3::<String, String, HashMap<String, String>>();
+            state.sent_messages.push((account_id.unwrap_or(String::new()),
+                                      destination.unwrap_or(String::new()),
+                                      payloads.unwrap_or(HashMap::new())));
+            let id = state.next_id;
+            state.next_id += 1;
+            reply.append1(id)
+        },
+        "lookupName" => {
+            let (account_id, _, name) = call.get3::<String, String, String>();
+            let (account_id, name) = (account_id.unwrap_or(String::new()), name.unwrap_or(String::new()));
+            let signal = match state.names.get(&name) {
+                Some(address) => FakeSignal::RegisteredNameFound(account_id, 0, address.clone(), name.clone()),
+                None => FakeSignal::RegisteredNameFound(account_id, 2, String::new(), name.clone()),
+            };
+            state.signals.push(signal);
+            reply.append1(true)
+        },
+        "lookupAddress" => {
+            let (account_id, _, address) = call.get3::<String, String, String>();
+            let (account_id, address) = (account_id.unwrap_or(String::new()), address.unwrap_or(String::new()));
+            let signal = match state.names.iter().find(|&(_, a)| *a == address) {
+                Some((name, _)) => FakeSignal::RegisteredNameFound(account_id, 0, address.clone(), name.clone()),
+                None => FakeSignal::RegisteredNameFound(account_id, 2, address.clone(), String::new()),
+            };
+            state.signals.push(signal);
+            reply.append1(true)
+        },
+        "registerName" => {
+            let (account_id, _, name) = call.get3::<String, String, String>();
+            let (account_id, name) = (account_id.unwrap_or(String::new()), name.unwrap_or(String::new()));
+            let address = state.account_mut(&account_id).and_then(|d| d.get("Account.username").cloned());
+            let result = match address {
+                Some(_) if state.names.contains_key(&name) => 3,
+                Some(address) => {
+                    state.names.insert(name.clone(), address);
+                    0
+                },
+                None => 4,
+            };
+            state.signals.push(FakeSignal::NameRegistrationEnded(account_id, result, name));
+            reply.append1(true)
+        },
+        "getKnownRingDevices" => {
+            let account_id: String = call.get1().unwrap_or_default();
+            let devices = state.devices.get(&account_id).cloned().unwrap_or(HashMap::new());
+            reply.append1(devices)
+        },
+        "revokeDevice" => {
+            let (account_id, password, device_id) = call.get3::<String, String, String>();
+            let (account_id, device_id) = (account_id.unwrap_or(String::new()), device_id.unwrap_or(String::new()));
+            if state.account_mut(&account_id).is_none() {
+                return reply.append1(false);
+            }
+            let status = if password.unwrap_or(String::new()) != state.passwords.get(&account_id).cloned().unwrap_or(String::new()) {
+                1
+            } else {
+                let devices = state.devices.entry(account_id.clone()).or_default();
+                if devices.remove(&device_id).is_some() {0} else {2}
+            };
+            if status == 0 {
+                let devices = state.devices.get(&account_id).cloned().unwrap_or(HashMap::new());
+                state.signals.push(FakeSignal::KnownDevicesChanged(account_id.clone(), devices));
+            }
+            state.signals.push(FakeSignal::DeviceRevocationEnded(account_id, device_id, status));
+            reply.append1(true)
+        },
+        "exportOnRing" => {
+            let (account_id, password) = call.get2::<String, String>();
+            let account_id = account_id.unwrap_or(String::new());
+            if state.account_mut(&account_id).is_none() {
+                return reply.append1(false);
+            }
+            let signal = if password.unwrap_or(String::new()) != state.passwords.get(&account_id).cloned().unwrap_or(String::new()) {
+                FakeSignal::ExportOnRingEnded(account_id, 1, String::new())
+            } else {
+                let pin = format!("{:08x}", state.next_id);
+                state.next_id += 1;
+                FakeSignal::ExportOnRingEnded(account_id, 0, pin)
+            };
+            state.signals.push(signal);
+            reply.append1(true)
+        },
+        "sendTrustRequest" => {
+            let (account_id, destination) = call.get2::<String, String>();
+            state.sent_requests.push((account_id.unwrap_or(String::new()), destination.unwrap_or(String::new())));
+            reply
+        },
+        "addContact" => {
+            let (account_id, contact) = call.get2::<String, String>();
+            state.contacts.entry(account_id.unwrap_or(String::new())).or_default()
+                          .push(contact.unwrap_or(String::new()));
+            reply
+        },
+        "removeContact" => {
+            let (account_id, contact) = call.get2::<String, String>();
+            let contact = contact.unwrap_or(String::new());
+            if let Some(contacts) = state.contacts.get_mut(&*account_id.unwrap_or(String::new())) {
+                contacts.retain(|c| *c != contact);
+            }
+            reply
+        },
+        "getContacts" => {
+            let account_id: String = call.get1().unwrap_or_default();
+            reply.append1(details_list(state.contacts.get(&account_id), "id"))
+        },
+        "getTrustRequests" => {
+            let account_id: String = call.get1().unwrap_or_default();
+            reply.append1(details_list(state.requests.get(&account_id), "from"))
+        },
+        "acceptTrustRequest" | "discardTrustRequest" => {
+            let (account_id, from) = call.get2::<String, String>();
+            let (account_id, from) = (account_id.unwrap_or(String::new()), from.unwrap_or(String::new()));
+            let mut found = false;
+            if let Some(requests) = state.requests.get_mut(&account_id) {
+                found = requests.contains(&from);
+                requests.retain(|r| *r != from);
+            }
+            if found && member == "acceptTrustRequest" {
+                state.contacts.entry(account_id).or_default().push(from);
+            }
+            reply.append1(found)
+        },
+        _ => unknown_method(call, &member),
+    }
+}
+
+/**
+ * Build the list of details returned by getContacts and getTrustRequests
+ * @param ids the contacts or requests
+ * @param key of each id in its details
+ * @return a list of one-entry dictionaries
+ */
+fn details_list(ids: Option<&Vec<String>>, key: &str) -> Vec<HashMap<String, String>> {
+    ids.map(|ids| ids.iter().map(|id| {
+        let mut details = HashMap::new();
+        details.insert(key.to_string(), id.clone());
+        details
+    }).collect()).unwrap_or_default()
+}
+
+fn unknown_method(call: &Message, member: &str) -> Message {
+    Message::new_error(call, "org.freedesktop.DBus.Error.UnknownMethod",
+                       &format!("Unknown method {}", member)).unwrap()
+}
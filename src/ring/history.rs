@@ -0,0 +1,556 @@
+use ring::api::call::{CallOutcome, CallRecord};
+use ring::api::interaction::{Direction, Interaction, MessageStatus};
+
+use std::cmp;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use time::{self, Duration, Timespec};
+
+/**
+ * Interactions saved on disk, one file per (account, peer) under
+ * <data dir>/history/<account id>/<peer>.log, ids being escaped (see file_name).
+ * Each line is a record: "I" for an interaction, "S" for a new delivery status.
+ * Records are only appended, so a crash never loses the past.
+ * Finished calls of every account go to <data dir>/calls.log, one "C" record per call,
+ * out of the history directory where an account could have the same name.
+ */
+pub struct HistoryStore {
+    root: PathBuf,
+    call_log: PathBuf,
+}
+
+impl HistoryStore {
+    /**
+     * Store in $XDG_DATA_HOME/ruring, or ~/.local/share/ruring
+     * @return None if no data directory can be found
+     */
+    pub fn new() -> Option<HistoryStore> {
+        let data_dir = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => {
+                let mut home = PathBuf::from(env::var_os("HOME")?);
+                home.push(".local");
+                home.push("share");
+                home
+            }
+        };
+        Some(HistoryStore::with_root(data_dir.join("ruring")))
+    }
+
+    /**
+     * @param root directory holding the history directory and the call log
+     */
+    pub fn with_root(root: PathBuf) -> HistoryStore {
+        HistoryStore {
+            call_log: root.join("calls.log"),
+            root: root.join("history"),
+        }
+    }
+
+    fn conversation_path(&self, account_id: &str, peer: &str) -> PathBuf {
+        self.root.join(file_name(account_id)).join(format!("{}.log", file_name(peer)))
+    }
+
+    fn append(&self, account_id: &str, peer: &str, record: &str) -> io::Result<()> {
+        append_to(self.conversation_path(account_id, peer), record)
+    }
+
+    /**
+     * Save a finished call at the end of the call log
+     * @param self
+     * @param record
+     */
+    pub fn save_call(&self, record: &CallRecord) -> io::Result<()> {
+        let direction = match record.direction {
+            Direction::Incoming => "in",
+            Direction::Outgoing => "out",
+        };
+        let line = format!("C\t{}\t{}\t{}\t{}\t{}\t{}\t{}", escape(&record.call_id), direction,
+                           escape(&record.account_id), escape(&record.peer), record.start.to_timespec().sec,
+                           record.duration.num_seconds(), record.outcome);
+        append_to(self.call_log.clone(), &line)
+    }
+
+    /**
+     * @param self
+     * @return the finished calls, oldest first
+     */
+    pub fn load_calls(&self) -> io::Result<Vec<CallRecord>> {
+        if !self.call_log.exists() {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for line in BufReader::new(File::open(&self.call_log)?).lines() {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            match parse_call(&fields) {
+                Some(record) => records.push(record),
+                None => warn!("Ignoring bad call record: {}", line),
+            }
+        }
+        Ok(records)
+    }
+
+    /**
+     * Save an interaction at the end of its conversation
+     * @param self
+     * @param account_id
+     * @param interaction
+     */
+    pub fn save(&self, account_id: &str, interaction: &Interaction) -> io::Result<()> {
+        let time = interaction.time.to_timespec();
+        let direction = match interaction.direction {
+            Direction::Incoming => "in",
+            Direction::Outgoing => "out",
+        };
+        let mut payloads: Vec<String> = interaction.payloads.iter()
+            .map(|(mime_type, content)| format!("{}={}", escape(mime_type), escape(content)))
+            .collect();
+        payloads.sort();
+        let record = format!("I\t{}\t{}\t{}\t{}.{}\t{}\t{}", interaction.id, direction, interaction.status,
+                             time.sec, time.nsec, escape(&interaction.author_ring_id), payloads.join("\t"));
+        self.append(account_id, &interaction.peer_ring_id, &record)
+    }
+
+    /**
+     * Save the new delivery status of a sent interaction
+     * @param self
+     * @param account_id
+     * @param peer
+     * @param id of the interaction
+     * @param status
+     */
+    pub fn save_status(&self, account_id: &str, peer: &str, id: u64, status: MessageStatus) -> io::Result<()> {
+        self.append(account_id, peer, &format!("S\t{}\t{}", id, status))
+    }
+
+    /**
+     * @param self
+     * @param account_id
+     * @return the peers having a conversation with this account
+     */
+    pub fn peers(&self, account_id: &str) -> io::Result<Vec<String>> {
+        let dir = self.root.join(file_name(account_id));
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut peers = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".log") {
+                peers.push(id_from_file_name(&name[..name.len() - 4]));
+            }
+        }
+        peers.sort();
+        Ok(peers)
+    }
+
+    /**
+     * Load a page of a conversation. The log is read from its end, up to the page.
+     * @param self
+     * @param account_id
+     * @param peer
+     * @param page 0 for the most recent interactions, 1 for the ones before...
+     * @param page_size
+     * @return the interactions of the page, oldest first
+     */
+    pub fn load(&self, account_id: &str, peer: &str, page: usize, page_size: usize) -> io::Result<Vec<Interaction>> {
+        let path = self.conversation_path(account_id, peer);
+        if !path.exists() || page_size == 0 {
+            return Ok(Vec::new());
+        }
+        let wanted = (page + 1) * page_size;
+        // Most recent first
+        let mut interactions: Vec<Interaction> = Vec::new();
+        // A status is saved after its interaction, so the first one found is the last one
+        let mut statuses: HashMap<u64, MessageStatus> = HashMap::new();
+        for line in ReverseLines::open(&path)? {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[0] {
+                "I" => match parse_interaction(peer, &fields) {
+                    Some(interaction) => {
+                        interactions.push(interaction);
+                        if interactions.len() == wanted {
+                            break;
+                        }
+                    },
+                    None => warn!("Ignoring bad history record: {}", line),
+                },
+                "S" if fields.len() == 3 => {
+                    if let (Ok(id), Some(status)) = (fields[1].parse(), parse_status(fields[2])) {
+                        statuses.entry(id).or_insert(status);
+                    }
+                },
+                _ => warn!("Ignoring bad history record: {}", line),
+            }
+        }
+        let mut result: Vec<Interaction> = interactions.into_iter().skip(page * page_size).collect();
+        result.reverse();
+        for interaction in result.iter_mut() {
+            if interaction.direction == Direction::Outgoing && interaction.id != 0 {
+                if let Some(status) = statuses.get(&interaction.id) {
+                    interaction.status = *status;
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+// Bytes read at once by ReverseLines
+const BLOCK_SIZE: u64 = 8192;
+
+/**
+ * Lines of a file, last one first, read by blocks from its end
+ */
+struct ReverseLines {
+    file: File,
+    // Where the bytes not read yet end
+    pos: u64,
+    // Start of the first line read, which may begin in the previous block
+    partial: Vec<u8>,
+    // Complete lines of the last block read, in file order
+    lines: Vec<String>,
+}
+
+impl ReverseLines {
+    fn open(path: &Path) -> io::Result<ReverseLines> {
+        let file = File::open(path)?;
+        let pos = file.metadata()?.len();
+        Ok(ReverseLines {
+            file,
+            pos,
+            partial: Vec::new(),
+            lines: Vec::new(),
+        })
+    }
+
+    fn read_block(&mut self) -> io::Result<()> {
+        let size = cmp::min(BLOCK_SIZE, self.pos);
+        self.pos -= size;
+        self.file.seek(SeekFrom::Start(self.pos))?;
+        let mut block = vec![0; size as usize];
+        self.file.read_exact(&mut block)?;
+        block.extend_from_slice(&self.partial);
+        let mut parts = block.split(|byte| *byte == b'\n');
+        self.partial = parts.next().unwrap_or(&[]).to_vec();
+        self.lines = parts.map(|line| String::from_utf8_lossy(line).into_owned()).collect();
+        Ok(())
+    }
+}
+
+impl Iterator for ReverseLines {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        loop {
+            if let Some(line) = self.lines.pop() {
+                return Some(Ok(line));
+            }
+            if self.pos == 0 {
+                if self.partial.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&self.partial).into_owned();
+                self.partial.clear();
+                return Some(Ok(line));
+            }
+            if let Err(e) = self.read_block() {
+                self.pos = 0;
+                self.partial.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+fn parse_interaction(peer: &str, fields: &[&str]) -> Option<Interaction> {
+    if fields.len() < 6 {
+        return None;
+    }
+    let direction = match fields[2] {
+        "in" => Direction::Incoming,
+        "out" => Direction::Outgoing,
+        _ => return None,
+    };
+    let mut timestamp = fields[4].split('.');
+    let sec: i64 = timestamp.next()?.parse().ok()?;
+    let nsec: i32 = timestamp.next().unwrap_or("0").parse().ok()?;
+    let mut payloads = HashMap::new();
+    for payload in fields[6..].iter().filter(|p| !p.is_empty()) {
+        let mut payload = payload.splitn(2, '=');
+        let mime_type = unescape(payload.next()?);
+        payloads.insert(mime_type, unescape(payload.next()?));
+    }
+    Some(Interaction {
+        id: fields[1].parse().ok()?,
+        author_ring_id: unescape(fields[5]),
+        peer_ring_id: peer.to_string(),
+        payloads,
+        time: time::at(Timespec::new(sec, nsec)),
+        direction,
+        status: parse_status(fields[3])?,
+    })
+}
+
+fn parse_call(fields: &[&str]) -> Option<CallRecord> {
+    if fields.len() != 8 || fields[0] != "C" {
+        return None;
+    }
+    let direction = match fields[2] {
+        "in" => Direction::Incoming,
+        "out" => Direction::Outgoing,
+        _ => return None,
+    };
+    let outcome = match fields[7] {
+        "answered" => CallOutcome::Answered,
+        "missed" => CallOutcome::Missed,
+        "refused" => CallOutcome::Refused,
+        "failed" => CallOutcome::Failed,
+        _ => return None,
+    };
+    Some(CallRecord {
+        call_id: unescape(fields[1]),
+        account_id: unescape(fields[3]),
+        peer: unescape(fields[4]),
+        direction,
+        start: time::at(Timespec::new(fields[5].parse().ok()?, 0)),
+        duration: Duration::seconds(fields[6].parse().ok()?),
+        outcome,
+    })
+}
+
+fn parse_status(status: &str) -> Option<MessageStatus> {
+    match status {
+        "sending" => Some(MessageStatus::Sending),
+        "sent" => Some(MessageStatus::Sent),
+        "delivered" => Some(MessageStatus::Delivered),
+        "read" => Some(MessageStatus::Read),
+        "failure" => Some(MessageStatus::Failure),
+        _ => None
+    }
+}
+
+fn append_to(path: PathBuf, record: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", record)
+}
+
+// Ids become file names: to keep them in their directory, and out of special names
+// like "..", bytes other than [A-Za-z0-9-_@+:] and non leading dots are written %XX
+fn file_name(id: &str) -> String {
+    if id.is_empty() {
+        return String::from("%");
+    }
+    let mut name = String::new();
+    for (idx, byte) in id.bytes().enumerate() {
+        let c = byte as char;
+        if c.is_ascii_alphanumeric() || "-_@+:".contains(c) || (c == '.' && idx != 0) {
+            name.push(c);
+        } else {
+            name += &*format!("%{:02X}", byte);
+        }
+    }
+    name
+}
+
+fn id_from_file_name(name: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut idx = 0;
+    while idx < name.len() {
+        let byte = name.as_bytes()[idx];
+        if byte == b'%' {
+            if let Some(Ok(escaped)) = name.get(idx + 1..idx + 3).map(|hex| u8::from_str_radix(hex, 16)) {
+                bytes.push(escaped);
+                idx += 3;
+                continue;
+            }
+        } else {
+            bytes.push(byte);
+        }
+        idx += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// Records are tab separated lines, so tabs, newlines and '=' are escaped
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r").replace('=', "\\e")
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('e') => result.push('='),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::api::call::{CallOutcome, CallRecord};
+    use ring::api::interaction::{Direction, Interaction, MessageStatus};
+    use ring::history::HistoryStore;
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+    use time::{self, Duration};
+
+    fn temp_store(name: &str) -> (HistoryStore, PathBuf) {
+        let root = env::temp_dir().join(format!("ruring-test-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        (HistoryStore::with_root(root.clone()), root)
+    }
+
+    fn interaction(id: u64, text: &str, direction: Direction) -> Interaction {
+        let mut payloads = HashMap::new();
+        payloads.insert(String::from("text/plain"), text.to_string());
+        Interaction {
+            id,
+            author_ring_id: String::from("a1b2c3"),
+            peer_ring_id: String::from("d4e5f6"),
+            payloads,
+            time: time::now(),
+            direction,
+            status: if direction == Direction::Outgoing {MessageStatus::Sent} else {MessageStatus::Delivered},
+        }
+    }
+
+    #[test]
+    fn interactions_are_saved_and_loaded() {
+        let (store, root) = temp_store("saved");
+        let message = interaction(1, "multi\tline\nmessage = \\o/", Direction::Outgoing);
+        store.save("acc", &message).unwrap();
+        store.save_status("acc", "d4e5f6", 1, MessageStatus::Read).unwrap();
+        store.save("acc", &interaction(0, "hi", Direction::Incoming)).unwrap();
+
+        assert_eq!(store.peers("acc").unwrap(), vec![String::from("d4e5f6")]);
+        let loaded = store.load("acc", "d4e5f6", 0, 10).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].text(), message.text());
+        assert_eq!(loaded[0].status, MessageStatus::Read);
+        assert_eq!(loaded[0].time.to_timespec(), message.time.to_timespec());
+        assert_eq!(loaded[1].direction, Direction::Incoming);
+        assert_eq!(loaded[1].peer_ring_id, "d4e5f6");
+        assert!(store.load("acc", "unknown", 0, 10).unwrap().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn conversations_are_loaded_by_pages() {
+        let (store, root) = temp_store("pages");
+        for i in 0..5 {
+            store.save("acc", &interaction(i + 1, &format!("{}", i), Direction::Outgoing)).unwrap();
+        }
+        let texts = |page: usize| -> Vec<String> {
+            store.load("acc", "d4e5f6", page, 2).unwrap().iter()
+                .map(|i| i.text().unwrap().to_string()).collect()
+        };
+        assert_eq!(texts(0), vec!["3", "4"]);
+        assert_eq!(texts(1), vec!["1", "2"]);
+        assert_eq!(texts(2), vec!["0"]);
+        assert!(texts(3).is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn ids_stay_in_the_history_directory() {
+        let (store, root) = temp_store("escape");
+        let ids = ["..", "a/../b", "/etc/passwd", ".hidden", "nul\0byte", "sip:carol@example.com", ""];
+        for id in ids.iter() {
+            store.save(id, &Interaction { peer_ring_id: id.to_string(), ..interaction(1, "hi", Direction::Incoming) })
+                 .unwrap();
+        }
+        let history = root.join("history");
+        for entry in fs::read_dir(&history).unwrap() {
+            let account_dir = entry.unwrap().path();
+            assert!(account_dir.is_dir(), "{:?} is not an account directory", account_dir);
+            assert_eq!(fs::read_dir(&account_dir).unwrap().count(), 1);
+        }
+        assert_eq!(fs::read_dir(&history).unwrap().count(), ids.len());
+        for id in ids.iter() {
+            assert_eq!(store.peers(id).unwrap(), vec![id.to_string()]);
+            assert_eq!(store.load(id, id, 0, 10).unwrap().len(), 1);
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn long_conversations_are_read_from_their_end() {
+        let (store, root) = temp_store("long");
+        // Several blocks of records
+        let text: String = (0..100).map(|_| 'x').collect();
+        for i in 0..300 {
+            store.save("acc", &interaction(i + 1, &format!("{} {}", i, text), Direction::Outgoing)).unwrap();
+        }
+        store.save_status("acc", "d4e5f6", 1, MessageStatus::Delivered).unwrap();
+        store.save_status("acc", "d4e5f6", 1, MessageStatus::Read).unwrap();
+        let page = store.load("acc", "d4e5f6", 0, 50).unwrap();
+        assert_eq!(page.len(), 50);
+        assert!(page[0].text().unwrap().starts_with("250 "));
+        assert!(page[49].text().unwrap().starts_with("299 "));
+        let first = store.load("acc", "d4e5f6", 5, 50).unwrap();
+        assert!(first[0].text().unwrap().starts_with("0 "));
+        assert_eq!(first[0].status, MessageStatus::Read);
+        assert_eq!(first[1].status, MessageStatus::Sent);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn calls_are_saved_and_loaded() {
+        let (store, root) = temp_store("calls");
+        assert!(store.load_calls().unwrap().is_empty());
+        let record = CallRecord {
+            call_id: String::from("42"),
+            account_id: String::from("acc"),
+            peer: String::from("d4e5f6"),
+            direction: Direction::Incoming,
+            start: time::now(),
+            duration: Duration::seconds(75),
+            outcome: CallOutcome::Answered,
+        };
+        store.save_call(&record).unwrap();
+        store.save_call(&CallRecord { outcome: CallOutcome::Missed, ..record.clone() }).unwrap();
+
+        let loaded = store.load_calls().unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].peer, "d4e5f6");
+        assert_eq!(loaded[0].direction, Direction::Incoming);
+        assert_eq!(loaded[0].start.to_timespec().sec, record.start.to_timespec().sec);
+        assert_eq!(loaded[0].duration, Duration::seconds(75));
+        assert_eq!(loaded[1].outcome, CallOutcome::Missed);
+        // The call log isn't a conversation
+        assert!(store.peers("acc").unwrap().is_empty());
+
+        // Nor in the way of an account named like it
+        store.save("calls.log", &interaction(1, "hi", Direction::Incoming)).unwrap();
+        store.save_call(&record).unwrap();
+        assert_eq!(store.load_calls().unwrap().len(), 3);
+        assert_eq!(store.load("calls.log", "d4e5f6", 0, 10).unwrap().len(), 1);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,11 +1,29 @@
-use ring::api::account::Account;
-use ring::api::interaction::Interaction;
+use ring::api::account::{Account, AccountSource, RegistrationState};
+use ring::api::account_details::{AccountDetails, SipSettings};
+use ring::api::call::{Call, CallRecord, CallState};
+use ring::api::conference::{Conference, ConferenceState};
+use ring::api::conversation::Conversation;
+use ring::api::destination::parse_destination;
+use ring::api::device::{devices_from_daemon, Device, RevocationStatus};
+use ring::api::event::RingEvent;
+use ring::api::interaction::{Direction, Interaction, MessageStatus, TEXT_PLAIN};
+use ring::api::name::{is_ring_id, LookupStatus, NameRegistrationState};
+use ring::api::transfer::{Transfer, TransferStatus, TRANSFER_INVALID_ARGUMENT, TRANSFER_IO_ERROR,
+                          TRANSFER_SUCCESS, TRANSFER_UNKNOWN};
+use ring::connection::BusConnection;
+use ring::daemon::RingDaemon;
+use ring::dbus_daemon::DBusDaemon;
+use ring::error::RingError;
+use ring::history::HistoryStore;
 
-use dbus::{Connection, ConnectionItem, BusType, Message};
-use dbus::arg::{Array, Dict};
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use time;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
 
 /**
  * This class is used to interface Ring daemon's to any client using this library.
@@ -13,480 +31,1998 @@ use time;
  */
 pub struct Manager {
     pub accounts: Vec<Account>,
-    pub messages: Vec<(String, Interaction)>,
+    pub conversations: Vec<Conversation>,
+    // ring id -> registered name, as found on the name server
+    pub names: HashMap<String, String>,
+    // Calls not over yet
+    pub calls: Vec<Call>,
+    pub conferences: Vec<Conference>,
+    // Finished calls, oldest first
+    pub call_log: Vec<CallRecord>,
+    // File transfers known by the daemon
+    pub transfers: Vec<Transfer>,
 
-    ring_dbus: &'static str,
-    configuration_path: &'static str,
-    configuration_iface: &'static str,
-    call_path: &'static str,
-    call_iface: &'static str,
+    daemon: Box<dyn RingDaemon + Send>,
+    subscribers: Vec<Sender<RingEvent>>,
+    history: Option<HistoryStore>,
+    // ring ids already looked up
+    looked_up: HashSet<String>,
+    // calls refused from this client, to log them as such
+    refused: HashSet<String>,
+    // accounts imported with a PIN, waiting to be registered
+    importing: HashSet<String>,
+    // events found while handling a signal, published after it
+    followups: Vec<RingEvent>,
 }
 
+// Interactions loaded per conversation from the history
+pub const HISTORY_PAGE_SIZE: usize = 50;
+
 // TODO connect to account signals to update this manager
 impl Manager {
-    pub fn new() -> Result<Manager, &'static str> {
+    pub fn new() -> Result<Manager, RingError> {
+        Manager::with_daemon(Box::new(DBusDaemon::new(BusConnection::session())))
+    }
+
+    /**
+     * Build a manager for a daemon living on another bus than the session one
+     * @param address of the bus
+     */
+    #[cfg(test)]
+    pub fn with_address(address: &str) -> Result<Manager, RingError> {
+        Manager::with_daemon(Box::new(DBusDaemon::new(BusConnection::with_address(address))))
+    }
+
+    /**
+     * Build a manager for any daemon
+     * @param daemon the backend to use
+     */
+    pub fn with_daemon(daemon: Box<dyn RingDaemon + Send>) -> Result<Manager, RingError> {
         let mut manager = Manager {
             accounts: Vec::new(),
-            messages: Vec::new(),
+            conversations: Vec::new(),
+            names: HashMap::new(),
+            calls: Vec::new(),
+            conferences: Vec::new(),
+            call_log: Vec::new(),
+            transfers: Vec::new(),
+
+            daemon,
+            subscribers: Vec::new(),
+            history: None,
+            looked_up: HashSet::new(),
+            refused: HashSet::new(),
+            importing: HashSet::new(),
+            followups: Vec::new(),
+        };
+
+        manager.get_account_list()?;
+        if let Err(e) = manager.refresh_calls() {
+            warn!("Can't get current calls: {}", e);
+        }
+        if let Err(e) = manager.refresh_conferences() {
+            warn!("Can't get current conferences: {}", e);
+        }
+        if let Err(e) = manager.refresh_transfers() {
+            warn!("Can't get file transfers: {}", e);
+        }
+
+        Ok(manager)
+    }
+
+    /**
+     * Listen from interresting events of the daemon and call handlers, until stop is set.
+     * Returns directly if the daemon doesn't emit signals.
+     * @param manager
+     * @param stop set to quit the loop
+     */
+    pub fn handle_signals(manager: Arc<Mutex<Manager>>, stop: Arc<AtomicBool>) {
+        let listener = manager.lock().unwrap().daemon.listener();
+        let mut listener = match listener {
+            Ok(Some(listener)) => listener,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Can't listen daemon's signals: {}", e);
+                return;
+            }
+        };
+        // For each event, update the manager and tell subscribers.
+        while !stop.load(Ordering::SeqCst) {
+            for event in listener.poll(100) {
+                let mut m = manager.lock().unwrap();
+                m.handle_event(&event);
+                m.publish(event);
+                for followup in m.followups.split_off(0) {
+                    m.publish(followup);
+                }
+            }
+        }
+    }
+
+    /**
+     * Receive the events of the daemon. The receiver is dropped from
+     * subscribers when it is dropped.
+     * @param self
+     * @return the receiving end of the events
+     */
+    pub fn subscribe(&mut self) -> Receiver<RingEvent> {
+        let (tx, rx) = channel();
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /**
+     * Send an event to all living subscribers
+     * @param self
+     * @param event
+     */
+    fn publish(&mut self, event: RingEvent) {
+        self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /**
+     * Update the manager after an event
+     * @param self
+     * @param event
+     */
+    fn handle_event(&mut self, event: &RingEvent) {
+        match *event {
+            RingEvent::IncomingMessage { ref account_id, ref interaction } => {
+                self.record(account_id, interaction);
+                self.conversation_mut(account_id, &interaction.peer_ring_id).push(interaction.clone());
+            },
+            RingEvent::AccountsChanged => {
+                if let Err(e) = self.get_account_list() {
+                    error!("Can't refresh accounts: {}", e);
+                }
+            },
+            RingEvent::RegistrationStateChanged { ref account_id, ref state } => {
+                for account in self.accounts.iter_mut() {
+                    if account.id == *account_id {
+                        account.registration = state.clone();
+                    }
+                }
+                // The daemon is done with the archive once the account leaves these states
+                let pending = matches!(*state, RegistrationState::Initializing | RegistrationState::Trying);
+                if !pending && self.importing.remove(account_id) {
+                    info!("Import of {} ended: {}", account_id, state);
+                    self.followups.push(RingEvent::AccountImportEnded {
+                        account_id: account_id.clone(),
+                        registration: state.clone(),
+                    });
+                }
+            },
+            RingEvent::MessageStatusChanged { ref account_id, message_id, status, .. } => {
+                let interactions = self.conversations.iter_mut()
+                    .filter(|c| c.account_id == *account_id)
+                    .flat_map(|c| c.interactions.iter_mut());
+                for interaction in interactions {
+                    if interaction.id == message_id && interaction.direction == Direction::Outgoing {
+                        interaction.status = status;
+                        if let Some(ref history) = self.history {
+                            if let Err(e) = history.save_status(account_id, &interaction.peer_ring_id, message_id, status) {
+                                warn!("Can't save message status: {}", e);
+                            }
+                        }
+                    }
+                }
+            },
+            RingEvent::RegisteredNameFound { status, ref address, ref name, .. } => {
+                if status == LookupStatus::Found {
+                    self.names.insert(address.clone(), name.clone());
+                }
+            },
+            RingEvent::NameRegistrationEnded { ref account_id, state, ref name } => {
+                if state == NameRegistrationState::Success {
+                    for account in self.accounts.iter_mut() {
+                        if account.id == *account_id {
+                            account.registered_name = name.clone();
+                            self.names.insert(account.ring_id.clone(), name.clone());
+                        }
+                    }
+                }
+            },
+            RingEvent::KnownDevicesChanged { ref account_id, ref devices } => {
+                for account in self.accounts.iter_mut().filter(|account| account.id == *account_id) {
+                    let current_id = account.details.others.get("Account.deviceID").cloned().unwrap_or(String::new());
+                    account.devices = devices_from_daemon(devices, &current_id);
+                }
+            },
+            RingEvent::DeviceRevocationEnded { ref account_id, ref device_id, status } => {
+                if status == RevocationStatus::Success {
+                    for account in self.accounts.iter_mut().filter(|account| account.id == *account_id) {
+                        account.devices.retain(|device| device.id != *device_id);
+                    }
+                }
+            },
+            RingEvent::ExportOnRingEnded { .. } => {},
+            RingEvent::AccountImportEnded { .. } => {},
+            RingEvent::IncomingCall { ref account_id, ref call_id, ref from } => {
+                if self.call(call_id).is_none() {
+                    self.calls.push(Call::new(call_id, account_id, from, Direction::Incoming, CallState::Incoming));
+                }
+            },
+            RingEvent::CallStateChanged { ref call_id, ref state, .. } => {
+                let known = self.call(call_id).is_some();
+                if state.is_over() {
+                    if let Some(call) = self.call(call_id).cloned() {
+                        let refused = self.refused.remove(call_id);
+                        self.log_call(CallRecord::from_call(&call, state, refused));
+                    }
+                    self.calls.retain(|call| call.id != *call_id);
+                    for conference in self.conferences.iter_mut() {
+                        conference.participants.retain(|participant| participant != call_id);
+                    }
+                } else if known {
+                    for call in self.calls.iter_mut().filter(|call| call.id == *call_id) {
+                        call.set_state(state.clone());
+                    }
+                } else {
+                    // A call placed by another client
+                    match self.get_call_details(call_id) {
+                        Ok(call) => self.calls.push(call),
+                        Err(e) => warn!("Can't get call {}: {}", call_id, e),
+                    }
+                }
+            },
+            RingEvent::ConferenceCreated => {
+                if let Err(e) = self.refresh_conferences() {
+                    warn!("Can't get conferences: {}", e);
+                }
+            },
+            RingEvent::ConferenceChanged { ref conference_id, ref state } => {
+                // Participants may have changed too
+                if let Err(e) = self.refresh_conferences() {
+                    warn!("Can't get conferences: {}", e);
+                }
+                for conference in self.conferences.iter_mut().filter(|c| c.id == *conference_id) {
+                    conference.state = state.clone();
+                }
+            },
+            RingEvent::ConferenceRemoved { ref conference_id } => {
+                self.conferences.retain(|conference| conference.id != *conference_id);
+            },
+            RingEvent::DataTransferEvent { transfer_id, status } => {
+                if let Err(e) = self.refresh_transfer(transfer_id) {
+                    warn!("Can't get transfer {}: {}", transfer_id, e);
+                    for transfer in self.transfers.iter_mut().filter(|t| t.id == transfer_id) {
+                        transfer.status = status;
+                    }
+                }
+            },
+            RingEvent::IncomingTrustRequest { .. } => {},
+        }
+    }
+
+    /**
+     * Save interactions and calls in this history from now on, and load the call log and
+     * the last interactions of each conversation of the known accounts
+     * @param self
+     * @param history
+     */
+    pub fn set_history(&mut self, history: HistoryStore) {
+        match history.load_calls() {
+            Ok(mut records) => {
+                records.append(&mut self.call_log);
+                self.call_log = records;
+            },
+            Err(e) => warn!("Can't read the call log: {}", e),
+        }
+        let mut loaded: Vec<(String, String, Vec<Interaction>)> = Vec::new();
+        for account in self.accounts.iter() {
+            let peers = history.peers(&account.id).unwrap_or_else(|e| {
+                warn!("Can't read history of {}: {}", account.id, e);
+                Vec::new()
+            });
+            for peer in peers {
+                match history.load(&account.id, &peer, 0, HISTORY_PAGE_SIZE) {
+                    Ok(interactions) => loaded.push((account.id.clone(), peer, interactions)),
+                    Err(e) => warn!("Can't read history with {}: {}", peer, e),
+                }
+            }
+        }
+        for (account_id, peer, interactions) in loaded {
+            self.conversation_mut(&account_id, &peer).prepend(interactions);
+        }
+        self.history = Some(history);
+    }
+
+    /**
+     * @param self
+     * @param account_id
+     * @param peer
+     * @return the conversation between the account and the peer, if they talked
+     */
+    pub fn conversation(&self, account_id: &str, peer: &str) -> Option<&Conversation> {
+        self.conversations.iter().find(|c| c.account_id == account_id && c.peer_ring_id == peer)
+    }
+
+    /**
+     * @param self
+     * @param account_id
+     * @param peer
+     * @return the conversation between the account and the peer, created if needed
+     */
+    pub fn conversation_mut(&mut self, account_id: &str, peer: &str) -> &mut Conversation {
+        let index = match self.conversations.iter().position(|c| c.account_id == account_id && c.peer_ring_id == peer) {
+            Some(index) => index,
+            None => {
+                self.conversations.push(Conversation::new(account_id, peer));
+                self.conversations.len() - 1
+            }
+        };
+        &mut self.conversations[index]
+    }
+
+    /**
+     * Mark every interaction with a peer as read
+     * @param self
+     * @param account_id
+     * @param peer
+     */
+    pub fn mark_read(&mut self, account_id: &str, peer: &str) {
+        if let Some(conversation) = self.conversations.iter_mut()
+                                        .find(|c| c.account_id == account_id && c.peer_ring_id == peer) {
+            conversation.mark_read();
+        }
+    }
+
+    /**
+     * Load the page of interactions older than the ones of a conversation from the history
+     * @param self
+     * @param account_id
+     * @param peer
+     * @return how many interactions were added to the conversation. 0 without history or once all are loaded.
+     */
+    pub fn load_history(&mut self, account_id: &str, peer: &str) -> usize {
+        let known = self.conversation(account_id, peer).map(|c| c.interactions.len()).unwrap_or(0);
+        let mut older = match self.history {
+            // The history ends with the interactions already known
+            Some(ref history) => history.load(account_id, peer, 0, known + HISTORY_PAGE_SIZE).unwrap_or_else(|e| {
+                warn!("Can't read history with {}: {}", peer, e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        let count = older.len().saturating_sub(known);
+        older.truncate(count);
+        if count > 0 {
+            self.conversation_mut(account_id, peer).prepend(older);
+        }
+        count
+    }
+
+    /**
+     * Save an interaction in the history, if any
+     * @param self
+     * @param account_id
+     * @param interaction
+     */
+    fn record(&self, account_id: &str, interaction: &Interaction) {
+        if let Some(ref history) = self.history {
+            if let Err(e) = history.save(account_id, interaction) {
+                warn!("Can't save interaction: {}", e);
+            }
+        }
+    }
+
+    /**
+     * Enable or not a Ring account
+     * @param self
+     * @param accountId
+     * @param enable if need to enable the account
+     */
+    pub fn enable_account(&self, account_id: &str, enable: bool) -> Result<(), RingError> {
+        self.daemon.enable_account(account_id, enable)
+    }
+
+// Private methos
+
+    /**
+     * Get current ring accounts
+     * @param self: the manager to modify
+     *
+     */
+    pub fn get_account_list(&mut self) -> Result<(), RingError> {
+        let mut account_list: Vec<Account> = Vec::new();
+        for account in self.daemon.get_account_list()? {
+            account_list.push(self.build_account(&account)?);
+        }
+        self.accounts = account_list;
+        Ok(())
+    }
+
+    /**
+     * Build a new account with an id from the daemon
+     * @param self
+     * @param id the account id to build
+     * @return the account retrieven
+     */
+    fn build_account(&self, id: &str) -> Result<Account, RingError> {
+        let details = self.get_account_details(id)?;
+        let volatile = self.daemon.get_volatile_account_details(id)?;
+        let volatile_detail = |key: &str| volatile.get(key).cloned().unwrap_or(String::new());
+        let registration = RegistrationState::from_daemon(
+            &volatile_detail("Account.registrationStatus"),
+            volatile_detail("Account.registrationCode").parse().unwrap_or(0),
+            &volatile_detail("Account.registrationDescription"));
+        // Old daemons only store the name with the other details
+        let mut registered_name = volatile_detail("Account.registeredName");
+        if registered_name.is_empty() {
+            registered_name = details.others.get("Account.registeredName").cloned().unwrap_or(String::new());
+        }
+        // SIP accounts have no devices
+        let devices = if details.account_type == "SIP" {
+            Vec::new()
+        } else {
+            self.get_known_devices(id).unwrap_or_else(|e| {
+                warn!("Can't get devices of {}: {}", id, e);
+                Vec::new()
+            })
+        };
+        Ok(Account {
+            id: id.to_owned(),
+            ring_id: details.username.clone(),
+            alias: details.alias.clone(),
+            registered_name,
+            enabled: details.enabled,
+            registration,
+            details,
+            devices,
+        })
+    }
+
+    /**
+     * Get the whole configuration of an account
+     * @param self
+     * @param account_id
+     * @return the details of the account
+     */
+    pub fn get_account_details(&self, account_id: &str) -> Result<AccountDetails, RingError> {
+        Ok(AccountDetails::from_map(&self.daemon.get_account_details(account_id)?))
+    }
+
+    /**
+     * Change the configuration of an account
+     * @param self
+     * @param account_id
+     * @param details the new configuration
+     */
+    pub fn set_account_details(&mut self, account_id: &str, details: &AccountDetails) -> Result<(), RingError> {
+        self.daemon.set_account_details(account_id, details.to_map())?;
+        let updated = self.build_account(account_id)?;
+        for account in self.accounts.iter_mut() {
+            if account.id == account_id {
+                *account = updated.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Add a RING account. When imported with a PIN, the daemon fetches the archive
+     * from the other device and an AccountImportEnded event tells how it went.
+     * @param self
+     * @param main_info alias, path of the archive or PIN, depending on source
+     * @param password of the archive
+     * @param source where the account comes from
+     * @return the new account
+     */
+    pub fn add_account(&mut self, main_info: &str, password: &str, source: AccountSource) -> Result<Account, RingError> {
+        let mut details: HashMap<String, String> = HashMap::new();
+        match source {
+            AccountSource::New => details.insert(String::from("Account.alias"), main_info.to_string()),
+            AccountSource::Archive => details.insert(String::from("Account.archivePath"), main_info.to_string()),
+            AccountSource::Pin => details.insert(String::from("Account.archivePin"), main_info.trim().to_string()),
+        };
+        details.insert(String::from("Account.type"), String::from("RING"));
+        details.insert(String::from("Account.archivePassword"), password.to_string());
+        let account_added = self.daemon.add_account(details)?;
+        info!("New account: {:?}", account_added);
+        if source == AccountSource::Pin {
+            self.importing.insert(account_added.clone());
+        }
+        self.build_account(&account_added)
+    }
+
+    /**
+     * Add a SIP account. Its registration comes with RegistrationStateChanged events.
+     * @param self
+     * @param settings of the account
+     * @return the new account
+     */
+    pub fn add_sip_account(&mut self, settings: &SipSettings) -> Result<Account, RingError> {
+        settings.check().map_err(RingError::InvalidAccount)?;
+        let account_added = self.daemon.add_account(settings.to_map())?;
+        info!("New SIP account: {:?}", account_added);
+        self.build_account(&account_added)
+    }
+
+    /**
+     * Back up an account to a file, to import it later or on another device
+     * @param self
+     * @param account_id
+     * @param path where to write the archive
+     * @param password of the account archive
+     * @return if the archive was written
+     */
+    pub fn export_to_file(&self, account_id: &str, path: &str, password: &str) -> Result<bool, RingError> {
+        let exported = self.daemon.export_to_file(account_id, path, password)?;
+        info!("Export of {} to {}: {}", account_id, path, exported);
+        Ok(exported)
+    }
+
+    /**
+     * @param self
+     * @param account_id
+     * @param old_password current password of the archive, empty if none
+     * @param new_password
+     * @return if the password was changed, false if old_password is wrong
+     */
+    pub fn change_account_password(&self, account_id: &str, old_password: &str, new_password: &str) -> Result<bool, RingError> {
+        self.daemon.change_account_password(account_id, old_password, new_password)
+    }
+
+    pub fn rm_account(&self, id: &str) -> Result<(), RingError> {
+        self.daemon.rm_account(id)?;
+        info!("Remove account: {:?}", id);
+        Ok(())
+    }
+
+    /**
+     * Send a text message
+     * @param self
+     * @param from account id
+     * @param destination ring id of the contact
+     * @param body
+     * @return the id of the message
+     */
+    pub fn send_interaction(&mut self, from: &str, destination: &str, body: &str) -> Result<u64, RingError> {
+        let mut payloads: HashMap<String, String> = HashMap::new();
+        payloads.insert(String::from(TEXT_PLAIN), body.to_string());
+        self.send_payloads(from, destination, payloads)
+    }
+
+    /**
+     * Send a message made of any MIME payloads and add it to its conversation, with its delivery status
+     * @param self
+     * @param from account id
+     * @param destination ring id of the contact
+     * @param payloads MIME type -> content
+     * @return the id of the message
+     */
+    pub fn send_payloads(&mut self, from: &str, destination: &str,
+                         payloads: HashMap<String, String>) -> Result<u64, RingError> {
+        let author_ring_id = self.accounts.iter().find(|a| a.id == from)
+                                 .map(|a| a.ring_id.clone()).unwrap_or(String::new());
+        let mut interaction = Interaction {
+            id: 0,
+            author_ring_id,
+            peer_ring_id: destination.to_string(),
+            payloads: payloads.clone(),
+            time: time::now(),
+            direction: Direction::Outgoing,
+            status: MessageStatus::Sending,
+        };
+        let result = self.daemon.send_text_message(from, destination, payloads);
+        match result {
+            Ok(id) => {
+                interaction.id = id;
+                interaction.status = MessageStatus::Sent;
+            },
+            Err(_) => interaction.status = MessageStatus::Failure,
+        }
+        self.record(from, &interaction);
+        self.conversation_mut(from, destination).push(interaction);
+        result
+    }
+
+
+    /**
+     * Ask the name server which ring id has a name. The answer comes as a RegisteredNameFound event.
+     * @param self
+     * @param account_id account asking
+     * @param name
+     * @return if the lookup started
+     */
+    pub fn lookup_name(&self, account_id: &str, name: &str) -> Result<bool, RingError> {
+        self.daemon.lookup_name(account_id, "", name)
+    }
+
+    /**
+     * Ask the name server which name has a ring id. The answer comes as a RegisteredNameFound event.
+     * @param self
+     * @param account_id account asking
+     * @param address ring id
+     * @return if the lookup started
+     */
+    pub fn lookup_address(&self, account_id: &str, address: &str) -> Result<bool, RingError> {
+        self.daemon.lookup_address(account_id, "", address)
+    }
+
+    /**
+     * Register a name for an account. The result comes as a NameRegistrationEnded event.
+     * @param self
+     * @param account_id
+     * @param password of the account
+     * @param name
+     * @return if the registration started
+     */
+    pub fn register_name(&self, account_id: &str, password: &str, name: &str) -> Result<bool, RingError> {
+        self.daemon.register_name(account_id, password, name)
+    }
+
+    /**
+     * @param self
+     * @param account_id
+     * @return the devices linked to the account, this one first
+     */
+    pub fn get_known_devices(&self, account_id: &str) -> Result<Vec<Device>, RingError> {
+        let devices = self.daemon.get_known_ring_devices(account_id)?;
+        let details = self.daemon.get_account_details(account_id)?;
+        let current_id = details.get("Account.deviceID").cloned().unwrap_or(String::new());
+        Ok(devices_from_daemon(&devices, &current_id))
+    }
+
+    /**
+     * Unlink a device, e.g. a lost one. The result comes with a DeviceRevocationEnded event
+     * @param self
+     * @param account_id
+     * @param password of the account
+     * @param device_id
+     * @return if the revocation started
+     */
+    pub fn revoke_device(&self, account_id: &str, password: &str, device_id: &str) -> Result<bool, RingError> {
+        self.daemon.revoke_device(account_id, password, device_id)
+    }
+
+    /**
+     * Get a PIN to link a new device to the account. The PIN comes with an ExportOnRingEnded event
+     * @param self
+     * @param account_id
+     * @param password of the account
+     * @return if the export started
+     */
+    pub fn export_on_ring(&self, account_id: &str, password: &str) -> Result<bool, RingError> {
+        self.daemon.export_on_ring(account_id, password)
+    }
+
+    /**
+     * Get the registered name of a ring id, looking it up the first time
+     * @param self
+     * @param account_id account used for the lookup
+     * @param address ring id
+     * @return the name if already known
+     */
+    pub fn registered_name(&mut self, account_id: &str, address: &str) -> Option<String> {
+        if let Some(name) = self.names.get(address) {
+            return Some(name.clone());
+        }
+        if !self.looked_up.contains(address) {
+            self.looked_up.insert(address.to_string());
+            if let Err(e) = self.lookup_address(account_id, address) {
+                warn!("Can't look {} up: {}", address, e);
+            }
+        }
+        None
+    }
+
+    /**
+     * Find the ring id of a name without waiting for the name server.
+     * @param self
+     * @param account_id account used for the lookup
+     * @param name a registered name, or already a ring id
+     * @return the ring id if already known, else None and the answer comes as a RegisteredNameFound event
+     */
+    pub fn resolve_name(&self, account_id: &str, name: &str) -> Result<Option<String>, RingError> {
+        if is_ring_id(name) {
+            return Ok(Some(name.to_string()));
+        }
+        if let Some((address, _)) = self.names.iter().find(|&(_, n)| n == name) {
+            return Ok(Some(address.clone()));
+        }
+        if !self.lookup_name(account_id, name)? {
+            return Err(RingError::NameNotFound(name.to_string()));
+        }
+        Ok(None)
+    }
+
+    /**
+     * Add a contact, and ask a RING contact to trust us back
+     * @param account_id
+     * @param contact ring id or SIP address
+     */
+    pub fn add_contact(&self, account_id: &str, contact: &str) -> Result<(), RingError> {
+        self.daemon.add_contact(account_id, contact)?;
+        if self.accounts.iter().any(|a| a.id == account_id && !a.is_sip()) {
+            self.daemon.send_trust_request(account_id, contact)?;
+        }
+        Ok(())
+    }
+
+    pub fn rm_contact(&self, account_id: &str, contact: &str, banned: bool) -> Result<(), RingError> {
+        self.daemon.rm_contact(account_id, contact, banned)
+    }
+
+    pub fn get_contacts(&self, account_id: &str) -> Result<Vec<String>, RingError> {
+        self.daemon.get_contacts(account_id)
+    }
+
+    /**
+     * @param self
+     * @param account_id
+     * @return the contacts of the account, the last one who talked first
+     */
+    pub fn get_contacts_by_activity(&self, account_id: &str) -> Result<Vec<String>, RingError> {
+        let mut contacts = self.get_contacts(account_id)?;
+        // Stable sort: contacts without conversation keep the daemon's order
+        contacts.sort_by_key(|contact| {
+            let last_activity = self.conversation(account_id, contact).and_then(|c| c.last_activity());
+            Reverse(last_activity.map(|time| time.to_timespec()))
+        });
+        Ok(contacts)
+    }
+
+    pub fn get_requests(&self, account_id: &str) -> Result<Vec<String>, RingError> {
+        self.daemon.get_requests(account_id)
+    }
+
+    pub fn accept_request(&self, account_id: &str, from: &str, accept: bool) -> Result<bool, RingError> {
+        self.daemon.accept_request(account_id, from, accept)
+    }
+
+    /**
+     * Call a contact and track the call
+     * @param self
+     * @param account_id
+     * @param destination ring id or uri for Ring accounts; uri, user@host or number for SIP accounts
+     * @return the call id
+     */
+    pub fn place_call(&mut self, account_id: &str, destination: &str) -> Result<String, RingError> {
+        let uri = self.destination_uri(account_id, destination)?;
+        let call_id = self.daemon.place_call(account_id, &uri)?;
+        if call_id.is_empty() {
+            return Err(RingError::InvalidDestination {
+                destination: destination.to_string(),
+                reason: String::from("refused by the daemon"),
+            });
+        }
+        self.calls.push(Call::new(&call_id, account_id, &uri, Direction::Outgoing, CallState::Connecting));
+        Ok(call_id)
+    }
+
+    /**
+     * @param self
+     * @param account_id the calling account
+     * @param destination as typed by the user
+     * @return the uri to call, with the scheme fitting the account
+     */
+    fn destination_uri(&self, account_id: &str, destination: &str) -> Result<String, RingError> {
+        let account_type = match self.accounts.iter().find(|account| account.id == account_id) {
+            Some(account) => account.details.account_type.clone(),
+            None => self.get_account_details(account_id)?.account_type,
+        };
+        parse_destination(&account_type, destination)
+    }
+
+    /**
+     * @param self
+     * @param call_id
+     * @return the tracked call
+     */
+    pub fn call(&self, call_id: &str) -> Option<&Call> {
+        self.calls.iter().find(|call| call.id == call_id)
+    }
+
+    /**
+     * Replace the tracked calls with the ones of the daemon
+     * @param self
+     */
+    pub fn refresh_calls(&mut self) -> Result<(), RingError> {
+        let mut calls = Vec::new();
+        for call_id in self.get_call_list()? {
+            calls.push(self.get_call_details(&call_id)?);
+        }
+        self.calls = calls;
+        Ok(())
+    }
+
+    /**
+     * @param self
+     * @return ids of the calls known by the daemon
+     */
+    pub fn get_call_list(&self) -> Result<Vec<String>, RingError> {
+        self.daemon.get_call_list()
+    }
+
+    /**
+     * @param self
+     * @param call_id
+     * @return the call, as the daemon knows it
+     */
+    pub fn get_call_details(&self, call_id: &str) -> Result<Call, RingError> {
+        let details = self.daemon.get_call_details(call_id)?;
+        if details.is_empty() {
+            return Err(RingError::UnknownCall(call_id.to_string()));
+        }
+        Ok(Call::from_details(call_id, &details))
+    }
+
+    pub fn accept_call(&self, call_id: &str) -> Result<(), RingError> {
+        known_call(call_id, self.daemon.accept(call_id)?)
+    }
+
+    pub fn refuse_call(&mut self, call_id: &str) -> Result<(), RingError> {
+        self.refused.insert(call_id.to_string());
+        let result = self.daemon.refuse(call_id).and_then(|found| known_call(call_id, found));
+        if result.is_err() {
+            self.refused.remove(call_id);
+        }
+        result
+    }
+
+    pub fn hang_up(&self, call_id: &str) -> Result<(), RingError> {
+        known_call(call_id, self.daemon.hang_up(call_id)?)
+    }
+
+    pub fn hold(&self, call_id: &str) -> Result<(), RingError> {
+        known_call(call_id, self.daemon.hold(call_id)?)
+    }
+
+    pub fn unhold(&self, call_id: &str) -> Result<(), RingError> {
+        known_call(call_id, self.daemon.unhold(call_id)?)
+    }
 
-            ring_dbus: "cx.ring.Ring",
-            configuration_path: "/cx/ring/Ring/ConfigurationManager",
-            configuration_iface: "cx.ring.Ring.ConfigurationManager",
-            call_path: "/cx/ring/Ring/CallManager",
-            call_iface: "cx.ring.Ring.CallManager",
-        };
+    /**
+     * Send tones in a call, e.g. to answer a voice menu
+     * @param self
+     * @param call_id the call in progress
+     * @param keys tones to send, characters which aren't tones are ignored
+     */
+    pub fn play_dtmf(&self, call_id: &str, keys: &str) -> Result<(), RingError> {
+        known_call(call_id, self.call(call_id).is_some())?;
+        for key in keys.chars().filter(|key| is_dtmf(*key)) {
+            self.daemon.play_dtmf(&key.to_string())?;
+        }
+        Ok(())
+    }
 
-        manager.get_account_list();
+    /**
+     * @param self
+     * @param id
+     * @return the tracked file transfer
+     */
+    pub fn data_transfer(&self, id: u64) -> Option<&Transfer> {
+        self.transfers.iter().find(|transfer| transfer.id == id)
+    }
 
-        Ok(manager)
+    /**
+     * Replace the tracked file transfers with the ones of the daemon
+     * @param self
+     */
+    pub fn refresh_transfers(&mut self) -> Result<(), RingError> {
+        let mut transfers = Vec::new();
+        for id in self.daemon.data_transfer_list()? {
+            let (error, info) = self.daemon.data_transfer_info(id)?;
+            // The transfer may be gone since the list was read
+            if error == TRANSFER_SUCCESS {
+                transfers.push(Transfer::from_info(id, info));
+            }
+        }
+        self.transfers = transfers;
+        Ok(())
     }
 
     /**
-     * Listen from interresting signals from dbus and call handlers
+     * Update, or start tracking, a file transfer
      * @param self
+     * @param id
      */
-    pub fn handle_signals(manager: Arc<Mutex<Manager>>) {
-        // Use another dbus connection to listen signals.
-        let dbus_listener = Connection::get_private(BusType::Session).unwrap();
-        dbus_listener.add_match("interface=cx.ring.Ring.ConfigurationManager,member=incomingAccountMessage").unwrap();
-        dbus_listener.add_match("interface=cx.ring.Ring.ConfigurationManager,member=incomingTrustRequest").unwrap();
-        dbus_listener.add_match("interface=cx.ring.Ring.ConfigurationManager,member=accountsChanged").unwrap();
-        dbus_listener.add_match("interface=cx.ring.Ring.ConfigurationManager,member=registrationStateChanged").unwrap();
-        // For each signals, call handlers.
-        for i in dbus_listener.iter(1) {
-            let mut m = manager.lock().unwrap();
-            m.handle_accounts_signals(&i);
-            m.handle_registration_changed(&i);
-            if let Some((account_id, interaction)) = m.handle_interactions(&i) {
-                println!("New interaction for {}: {}", account_id, interaction);
-                m.messages.push((account_id, interaction));
-            };
-            if let Some((account_id, from)) = m.handle_requests(&i) {
-                println!("New request for {}: {}", account_id, from);
-            };
+    pub fn refresh_transfer(&mut self, id: u64) -> Result<(), RingError> {
+        let (error, info) = self.daemon.data_transfer_info(id)?;
+        transfer_result(error, id)?;
+        let transfer = Transfer::from_info(id, info);
+        if let Some(known) = self.transfers.iter_mut().find(|t| t.id == id) {
+            *known = transfer;
+            return Ok(());
         }
+        self.transfers.push(transfer);
+        Ok(())
     }
 
     /**
-     * Enable or not a Ring account
+     * Offer a file to a contact
      * @param self
-     * @param accountId
-     * @param enable if need to enable the account
+     * @param account_id
+     * @param peer ring id of the contact
+     * @param path of the file to send
+     * @return the id of the transfer
      */
-    pub fn enable_account(&self, account_id: &str, enable: bool) {
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "sendRegister");
-        if !dbus_msg.is_ok() {
-            error!("sendRegister call fails. Please verify daemon's API.");
-            return;
+    pub fn send_file(&mut self, account_id: &str, peer: &str, path: &str) -> Result<u64, RingError> {
+        let metadata = fs::metadata(path).map_err(|e| RingError::TransferFailed(format!("{}: {}", path, e)))?;
+        if !metadata.is_file() {
+            return Err(RingError::TransferFailed(format!("{} is not a file", path)));
         }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return;
+        let display_name = Path::new(path).file_name().map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or(path.to_string());
+        let transfer = Transfer {
+            id: 0,
+            account_id: account_id.to_string(),
+            peer: peer.to_string(),
+            direction: Direction::Outgoing,
+            status: TransferStatus::Created,
+            display_name,
+            path: path.to_string(),
+            mime_type: String::from("application/octet-stream"),
+            total_size: metadata.len() as i64,
+            progress: 0,
+        };
+        let (error, id) = self.daemon.send_file(transfer.to_info())?;
+        transfer_result(error, id)?;
+        if self.data_transfer(id).is_none() {
+            self.transfers.push(Transfer { id, ..transfer });
         }
-        let dbus = conn.unwrap();
-        let _ = dbus.send_with_reply_and_block(
-            dbus_msg.unwrap().append2(account_id, enable), 2000);
+        Ok(id)
     }
 
-// Private methos
+    /**
+     * Accept a file offered by a contact
+     * @param self
+     * @param id of the transfer
+     * @param path where to save the file
+     */
+    pub fn accept_transfer(&mut self, id: u64, path: &str) -> Result<(), RingError> {
+        transfer_result(self.daemon.accept_file_transfer(id, path, 0)?, id)?;
+        if let Some(transfer) = self.transfers.iter_mut().find(|t| t.id == id) {
+            transfer.path = path.to_string();
+        }
+        Ok(())
+    }
 
     /**
-     * Get current ring accounts
-     * @param self: the manager to modify
-     *
+     * Stop a transfer, or refuse an incoming file
+     * @param self
+     * @param id of the transfer
      */
-    pub fn get_account_list(&mut self) {
-        let mut account_list: Vec<Account> = Vec::new();
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path,
-                                                self.configuration_iface,
-                                                "getAccountList");
-        if !dbus_msg.is_ok() {
-            error!("getAccountList fails. Please verify daemon's API.");
-            return;
-        }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return;
-        }
-        let dbus = conn.unwrap();
-        let response = dbus.send_with_reply_and_block(dbus_msg.unwrap(), 2000).unwrap();
-        // getAccountList returns one argument, which is an array of strings.
-        let accounts: Array<&str, _>  = match response.get1() {
-            Some(array) => array,
-            None => return
-        };
-        for account in accounts {
-            account_list.push(self.build_account(account));
+    pub fn cancel_transfer(&mut self, id: u64) -> Result<(), RingError> {
+        transfer_result(self.daemon.cancel_data_transfer(id)?, id)
+    }
+
+    /**
+     * Ask the daemon how far a transfer is
+     * @param self
+     * @param id of the transfer
+     * @return (bytes transferred, total size)
+     */
+    pub fn transfer_progress(&mut self, id: u64) -> Result<(i64, i64), RingError> {
+        let (error, total_size, progress) = self.daemon.data_transfer_bytes_progress(id)?;
+        transfer_result(error, id)?;
+        if let Some(transfer) = self.transfers.iter_mut().find(|t| t.id == id) {
+            transfer.total_size = total_size;
+            transfer.progress = progress;
         }
-        self.accounts = account_list;
+        Ok((progress, total_size))
     }
 
     /**
-     * Handle new interactions signals
+     * Update the progress of the ongoing transfers.
+     * The daemon doesn't tell it by itself
      * @param self
-     * @param ci
-     * @return (accountId, interaction)
      */
-    fn handle_interactions(&self, ci: &ConnectionItem) -> Option<(String, Interaction)> {
-        // Check signal
-        let msg = if let &ConnectionItem::Signal(ref signal) = ci { signal } else { return None };
-        if &*msg.interface().unwrap() != "cx.ring.Ring.ConfigurationManager" { return None };
-        if &*msg.member().unwrap() != "incomingAccountMessage" { return None };
-        // incomingAccountMessage return three arguments
-        let (account_id, author_ring_id, payloads) = msg.get3::<&str, &str, Dict<&str, &str, _>>();
-        let author_ring_id = author_ring_id.unwrap().to_string();
-        let mut body = String::new();
-        for detail in payloads.unwrap() {
-            match detail {
-                (key, value) => {
-                    if key == "text/plain" {
-                        body = value.to_string();
-                    }
-                }
+    pub fn refresh_progress(&mut self) {
+        let ongoing: Vec<u64> = self.transfers.iter()
+            .filter(|t| t.status == TransferStatus::Ongoing).map(|t| t.id).collect();
+        for id in ongoing {
+            if let Err(e) = self.transfer_progress(id) {
+                warn!("Can't get progress of transfer {}: {}", id, e);
             }
-        };
-        let interaction = Interaction {
-            author_ring_id: author_ring_id,
-            body: body,
-            time: time::now()
-        };
-        Some((account_id.unwrap().to_string(), interaction))
+        }
     }
 
-    fn handle_registration_changed(&mut self, ci: &ConnectionItem) {
-        // Check signal
-        let msg = if let &ConnectionItem::Signal(ref signal) = ci { signal } else { return };
-        if &*msg.interface().unwrap() != "cx.ring.Ring.ConfigurationManager" { return };
-        if &*msg.member().unwrap() != "registrationStateChanged" { return };
-        let (account_id, registration_state, _, _) = msg.get4::<&str, &str, u64, &str>();
-        for account in self.accounts.iter_mut() {
-            if account.id == account_id.unwrap_or("") {
-                account.enabled = registration_state.unwrap_or("") == "REGISTERED";
+    /**
+     * Add a finished call to the log, and save it
+     * @param self
+     * @param record
+     */
+    fn log_call(&mut self, record: CallRecord) {
+        if let Some(ref history) = self.history {
+            if let Err(e) = history.save_call(&record) {
+                warn!("Can't save call {}: {}", record.call_id, e);
             }
         }
+        self.call_log.push(record);
     }
 
-    fn handle_accounts_signals(&mut self, ci: &ConnectionItem) {
-        // Check signal
-        let msg = if let &ConnectionItem::Signal(ref signal) = ci { signal } else { return };
-        if &*msg.interface().unwrap() != "cx.ring.Ring.ConfigurationManager" { return };
-        if &*msg.member().unwrap() != "accountsChanged" { return };
-        self.get_account_list()
+    /**
+     * Mute or unmute the microphone in a call
+     * @param self
+     * @param call_id
+     * @param mute
+     */
+    pub fn mute(&mut self, call_id: &str, mute: bool) -> Result<(), RingError> {
+        known_call(call_id, self.daemon.mute_local_media(call_id, "AUDIO", mute)?)?;
+        if let Some(call) = self.calls.iter_mut().find(|call| call.id == call_id) {
+            call.muted = mute;
+        }
+        Ok(())
     }
 
     /**
-     * Handle new pending requests signals
+     * Transfer a call to someone else
      * @param self
-     * @param ci
-     * @return (accountId, from)
+     * @param call_id
+     * @param to destination, as for place_call
      */
-    fn handle_requests(&self, ci: &ConnectionItem) -> Option<(String, String)> {
-        // Check signal
-        let msg = if let &ConnectionItem::Signal(ref signal) = ci { signal } else { return None };
-        if &*msg.interface().unwrap() != "cx.ring.Ring.ConfigurationManager" { return None };
-        if &*msg.member().unwrap() != "incomingTrustRequest" { return None };
-        // incomingTrustRequest return three arguments
-        let (account_id, from, _, _) = msg.get4::<&str, &str, Dict<&str, &str, _>, u64>();
-        Some((account_id.unwrap().to_string(), from.unwrap().to_string()))
+    pub fn transfer(&self, call_id: &str, to: &str) -> Result<(), RingError> {
+        let account_id = match self.call(call_id) {
+            Some(call) => call.account_id.clone(),
+            None => return Err(RingError::UnknownCall(call_id.to_string())),
+        };
+        let uri = self.destination_uri(&account_id, to)?;
+        known_call(call_id, self.daemon.transfer(call_id, &uri)?)
     }
 
     /**
-     * Build a new account with an id from the daemon
      * @param self
-     * @param id the account id to build
-     * @return the account retrieven
+     * @param conference_id
+     * @return the tracked conference
      */
-    fn build_account(&self, id: &str) -> Account {
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path,
-                                                self.configuration_iface,
-                                                "getAccountDetails");
-        if !dbus_msg.is_ok() {
-            error!("getAccountDetails fails. Please verify daemon's API.");
-            return Account::null();
-        }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return Account::null();
-        }
-        let dbus = conn.unwrap();
-        let response = dbus.send_with_reply_and_block(
-                                           dbus_msg.unwrap().append1(id), 2000
-                                       ).unwrap();
-        let details: Dict<&str, &str, _> = match response.get1() {
-            Some(details) => details,
-            None => {
-                return Account::null();
-            }
+    pub fn conference(&self, conference_id: &str) -> Option<&Conference> {
+        self.conferences.iter().find(|conference| conference.id == conference_id)
+    }
+
+    /**
+     * @param self
+     * @param call_id
+     * @return the tracked conference containing this call
+     */
+    pub fn conference_of(&self, call_id: &str) -> Option<&Conference> {
+        self.conferences.iter().find(|conference| conference.participants.iter().any(|p| p == call_id))
+    }
+
+    /**
+     * Replace the tracked conferences with the ones of the daemon
+     * @param self
+     */
+    pub fn refresh_conferences(&mut self) -> Result<(), RingError> {
+        let mut conferences = Vec::new();
+        for conference_id in self.get_conference_list()? {
+            let details = self.daemon.get_conference_details(&conference_id)?;
+            let state = details.get("CONF_STATE").map(|s| s.as_str()).unwrap_or("");
+            conferences.push(Conference {
+                participants: self.daemon.get_participant_list(&conference_id)?,
+                state: ConferenceState::from_daemon(state),
+                id: conference_id,
+            });
+        }
+        self.conferences = conferences;
+        Ok(())
+    }
+
+    /**
+     * @param self
+     * @return ids of the conferences known by the daemon
+     */
+    pub fn get_conference_list(&self) -> Result<Vec<String>, RingError> {
+        self.daemon.get_conference_list()
+    }
+
+    /**
+     * Merge two calls. If the first one is in a conference, the second joins it
+     * @param self
+     * @param call_id
+     * @param other_call_id
+     */
+    pub fn join_calls(&mut self, call_id: &str, other_call_id: &str) -> Result<(), RingError> {
+        let found = match self.conference_of(call_id).map(|conference| conference.id.clone()) {
+            Some(conference_id) => self.daemon.add_participant(other_call_id, &conference_id)?,
+            None => self.daemon.join_participant(call_id, other_call_id)?,
         };
-        let mut alias = "";
-        let mut ring_id = "";
-        let mut enabled = true;
-        for detail in details {
-            match detail {
-                (key, value) => {
-                    if key == "Account.enable" {
-                        enabled = value == "true";
-                    }
-                    if key == "Account.alias" {
-                        alias = value;
-                    }
-                    if key == "Account.username" {
-                        ring_id = value;
-                    }
-                }
-            }
+        known_call(other_call_id, found)?;
+        self.refresh_conferences()
+    }
+
+    /**
+     * Call several contacts at once
+     * @param self
+     * @param account_id the calling account
+     * @param participants destinations, as typed by the user
+     */
+    pub fn create_conference(&mut self, account_id: &str, participants: &[String]) -> Result<(), RingError> {
+        let mut uris = Vec::new();
+        for participant in participants {
+            uris.push(self.destination_uri(account_id, participant)?);
         }
-        Account {
-            id: id.to_owned(),
-            ring_id: ring_id.to_string(),
-            alias: alias.to_string(),
-            enabled: enabled,
+        self.daemon.create_conf_from_participant_list(uris)?;
+        self.refresh_calls()?;
+        self.refresh_conferences()
+    }
+
+    /**
+     * Add a call to an existing conference
+     * @param self
+     * @param call_id
+     * @param conference_id
+     */
+    pub fn add_to_conference(&mut self, call_id: &str, conference_id: &str) -> Result<(), RingError> {
+        if self.conference(conference_id).is_none() {
+            return Err(RingError::UnknownConference(conference_id.to_string()));
+        }
+        known_call(call_id, self.daemon.add_participant(call_id, conference_id)?)?;
+        self.refresh_conferences()
+    }
+
+    /**
+     * Take a call out of its conference, the call goes on alone
+     * @param self
+     * @param call_id
+     */
+    pub fn detach_call(&mut self, call_id: &str) -> Result<(), RingError> {
+        known_call(call_id, self.daemon.detach_participant(call_id)?)?;
+        self.refresh_conferences()
+    }
+
+    /**
+     * Hang up every call of a conference
+     * @param self
+     * @param conference_id
+     */
+    pub fn hang_up_conference(&mut self, conference_id: &str) -> Result<(), RingError> {
+        if !self.daemon.hang_up_conference(conference_id)? {
+            return Err(RingError::UnknownConference(conference_id.to_string()));
         }
+        self.refresh_conferences()
     }
+}
+
+/**
+ * @param key
+ * @return if the daemon can play this key as a tone
+ */
+pub fn is_dtmf(key: char) -> bool {
+    key.is_ascii_digit() || "*#ABCD".contains(key)
+}
 
+/**
+ * @param error DataTransferError returned by the daemon
+ * @param id of the transfer
+ * @return the error matching the one of the daemon
+ */
+fn transfer_result(error: u32, id: u64) -> Result<(), RingError> {
+    match error {
+        TRANSFER_SUCCESS => Ok(()),
+        TRANSFER_UNKNOWN => Err(RingError::UnknownTransfer(id)),
+        TRANSFER_IO_ERROR => Err(RingError::TransferFailed(format!("io error in transfer {}", id))),
+        TRANSFER_INVALID_ARGUMENT => Err(RingError::TransferFailed(format!("invalid argument for transfer {}", id))),
+        other => Err(RingError::TransferFailed(format!("error {} in transfer {}", other, id))),
+    }
+}
 
+/**
+ * @param call_id
+ * @param found as returned by the daemon
+ * @return an error if the daemon didn't know the call
+ */
+fn known_call(call_id: &str, found: bool) -> Result<(), RingError> {
+    if found {
+        Ok(())
+    } else {
+        Err(RingError::UnknownCall(call_id.to_string()))
+    }
+}
 
+#[cfg(test)]
+mod tests {
+    use ring::api::account::{AccountSource, RegistrationError, RegistrationState};
+    use ring::api::account_details::{SipSettings, SipTransport};
+    use ring::api::call::{CallOutcome, CallState};
+    use ring::api::conference::ConferenceState;
+    use ring::api::device::{ExportOnRingStatus, RevocationStatus};
+    use ring::api::event::RingEvent;
+    use ring::api::interaction::{Direction, MessageStatus};
+    use ring::api::name::LookupStatus;
+    use ring::api::transfer::TransferStatus;
+    use ring::dbus_daemon::DaemonNames;
+    use ring::error::RingError;
+    use ring::fake_daemon::FakeDaemon;
+    use ring::history::HistoryStore;
+    use ring::manager::{Manager, HISTORY_PAGE_SIZE};
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::process;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Receiver;
+    use std::thread;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
 
+    // Ring ids of the peers
+    const BOB: &str = "d4e5f6d4e5f6d4e5f6d4e5f6d4e5f6d4e5f6d4e5";
+    const CAROL: &str = "0a0b0c0a0b0c0a0b0c0a0b0c0a0b0c0a0b0c0a0b";
+    const DAVE: &str = "1a1b1c1a1b1c1a1b1c1a1b1c1a1b1c1a1b1c1a1b";
 
-    pub fn add_account(&self, main_info: &str, password: &str, from_archive: bool) -> Account {
-        let mut details: HashMap<&str, &str> = HashMap::new();
-        if from_archive {
-            details.insert("Account.archivePath", main_info);
-        } else {
-            details.insert("Account.alias", main_info);
-        }
-        details.insert("Account.type", "RING");
-        details.insert("Account.archivePassword", password);
-        let details = Dict::new(details.iter());
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "addAccount");
-        if !dbus_msg.is_ok() {
-            error!("addAccount fails. Please verify daemon's API.");
-            return Account::null();
-        }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return Account::null();
-        }
-        let dbus = conn.unwrap();
-        let response = dbus.send_with_reply_and_block(dbus_msg.unwrap()
-                                                                .append1(details), 2000).unwrap();
-        // addAccount returns one argument, which is a string.
-        let account_added: &str  = match response.get1() {
-            Some(account) => account,
-            None => ""
-        };
-        info!("New account: {:?}", account_added);
-        self.build_account(account_added)
+    /**
+     * Run handle_signals in a thread, stopped by the returned flag
+     */
+    fn listen(manager: &Arc<Mutex<Manager>>) -> (Arc<AtomicBool>, JoinHandle<()>) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let listener = manager.clone();
+        let listener_stop = stop.clone();
+        let handle = thread::spawn(move || Manager::handle_signals(listener, listener_stop));
+        // Let the listener subscribe before emitting
+        thread::sleep(Duration::from_millis(100));
+        (stop, handle)
     }
 
-    pub fn rm_account(&self, id: &str) {
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "removeAccount");
-        if !dbus_msg.is_ok() {
-            error!("removeAccount fails. Please verify daemon's API.");
-            return;
-        }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return;
+    /**
+     * Wait for a condition on the manager, updated by handle_signals
+     */
+    fn wait_for<F>(manager: &Arc<Mutex<Manager>>, condition: F) -> bool
+        where F: Fn(&Manager) -> bool
+    {
+        for _ in 0..200 {
+            if condition(&manager.lock().unwrap()) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
         }
-        let dbus = conn.unwrap();
-        let _ = dbus.send_with_reply_and_block(dbus_msg.unwrap().append1(id), 2000);
-        info!("Remove account: {:?}", id);
+        false
+    }
+
+    #[test]
+    fn accounts_are_loaded_from_the_daemon() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = daemon.manager();
+        assert_eq!(manager.accounts.len(), 1);
+        assert_eq!(manager.accounts[0].id, id);
+        assert_eq!(manager.accounts[0].alias, "alice");
+        assert_eq!(manager.accounts[0].ring_id, "a1b2c3");
+    }
+
+    #[test]
+    fn add_and_remove_account() {
+        let daemon = FakeDaemon::start();
+        let mut manager = daemon.manager();
+        let account = manager.add_account("bob", "", AccountSource::New).unwrap();
+        assert_eq!(account.alias, "bob");
+        assert_eq!(daemon.state().accounts.len(), 1);
+        manager.rm_account(&account.id).unwrap();
+        assert_eq!(daemon.state().accounts.len(), 0);
     }
 
-    pub fn send_interaction(&self, from: &str, destination: &str, body: &str) -> u64 {
-        let mut payloads: HashMap<&str, &str> = HashMap::new();
-        payloads.insert("text/plain", body);
-        let payloads = Dict::new(payloads.iter());
+    #[test]
+    fn sip_accounts_are_created_and_registered() {
+        let daemon = FakeDaemon::start();
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let (stop, listener) = listen(&manager);
 
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "sendTextMessage");
-        if !dbus_msg.is_ok() {
-            error!("sendTextMessage fails. Please verify daemon's API.");
-            return 0;
+        let mut settings = SipSettings::new("pbx.example.com", "", "secret");
+        settings.transport = SipTransport::Tls;
+        settings.tls_listener_port = 5061;
+        settings.srtp = true;
+        match manager.lock().unwrap().add_sip_account(&settings) {
+            Err(RingError::InvalidAccount(_)) => {},
+            other => panic!("unexpected result: {:?}", other),
         }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return 0;
+        settings.username = String::from("1001");
+        let account = manager.lock().unwrap().add_sip_account(&settings).unwrap();
+        assert!(account.is_sip());
+        assert_eq!(account.alias, "1001@pbx.example.com");
+        assert!(account.devices.is_empty());
+        {
+            let state = daemon.state();
+            let stored = &state.accounts[0].1;
+            assert_eq!(stored.get("Account.type").unwrap(), "SIP");
+            assert_eq!(stored.get("Account.hostname").unwrap(), "pbx.example.com");
+            assert_eq!(stored.get("Account.password").unwrap(), "secret");
+            assert_eq!(stored.get("TLS.enable").unwrap(), "true");
+            assert_eq!(stored.get("TLS.listenerPort").unwrap(), "5061");
+            assert_eq!(stored.get("SRTP.keyExchange").unwrap(), "sdes");
         }
-        let dbus = conn.unwrap();
-        let response = dbus.send_with_reply_and_block(dbus_msg.unwrap().append3(from, destination, payloads), 2000).unwrap();
-        // sendTextMessage returns one argument, which is a u64.
-        let interaction_id: u64  = match response.get1() {
-            Some(interaction_id) => interaction_id,
-            None => 0
-        };
-        interaction_id
+
+        daemon.emit_accounts_changed();
+        assert!(wait_for(&manager, |m| m.accounts.len() == 1));
+        assert_eq!(manager.lock().unwrap().accounts[0].details.srtp_enabled, Some(true));
+        daemon.emit_registration_state(&account.id, "ERROR_AUTH", 403, "Forbidden");
+        assert!(wait_for(&manager, |m| m.accounts[0].registration.is_error()));
+        daemon.emit_registration_state(&account.id, "REGISTERED", 200, "OK");
+        assert!(wait_for(&manager, |m| m.accounts[0].registration == RegistrationState::Registered));
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn archives_are_exported_with_their_password() {
+        let root = env::temp_dir().join(format!("ruring-test-{}-archives", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let archive = root.join("alice.gz");
+        let archive = archive.to_str().unwrap();
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = daemon.manager();
+
+        // No password yet
+        assert!(!manager.change_account_password(&id, "wrong", "secret").unwrap());
+        assert!(manager.change_account_password(&id, "", "secret").unwrap());
+        assert_eq!(daemon.state().passwords.get(&id).unwrap(), "secret");
+
+        assert!(!manager.export_to_file(&id, archive, "").unwrap());
+        assert!(!Path::new(archive).exists());
+        assert!(manager.export_to_file(&id, archive, "secret").unwrap());
+        assert!(Path::new(archive).exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn account_details_can_be_modified() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        daemon.state().accounts[0].1.insert(String::from("Account.unknownDetail"), String::from("kept"));
+        let mut manager = daemon.manager();
+        let mut details = manager.get_account_details(&id).unwrap();
+        assert_eq!(details.alias, "alice");
+        assert_ne!(details.turn_enabled, Some(true));
+
+        details.turn_enabled = Some(true);
+        details.turn_server = Some(String::from("turn.example.org"));
+        assert!(details.set("DHT.port", "not a port").is_err());
+        details.set("DHT.port", "4222").unwrap();
+        manager.set_account_details(&id, &details).unwrap();
+
+        let stored = daemon.state().accounts[0].1.clone();
+        assert_eq!(stored.get("TURN.enable"), Some(&String::from("true")));
+        assert_eq!(stored.get("TURN.server"), Some(&String::from("turn.example.org")));
+        assert_eq!(stored.get("DHT.port"), Some(&String::from("4222")));
+        assert_eq!(stored.get("Account.unknownDetail"), Some(&String::from("kept")));
+        assert_eq!(manager.accounts[0].details.turn_server, Some(String::from("turn.example.org")));
     }
 
+    #[test]
+    fn contacts_and_requests() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = daemon.manager();
+        assert_eq!(manager.get_contacts(&id).unwrap(), Vec::<String>::new());
+        manager.add_contact(&id, BOB).unwrap();
+        assert_eq!(manager.get_contacts(&id).unwrap(), vec![String::from(BOB)]);
+        assert_eq!(daemon.state().sent_requests, vec![(id.clone(), String::from(BOB))]);
 
-    pub fn send_trust_request(&self, from: &str, destination: &str) {
-        // TODO image
-        let buf = &[0x00u8];
-        let payloads = Array::new(buf.iter());
+        daemon.state().requests.insert(id.clone(), vec![String::from(CAROL)]);
+        assert_eq!(manager.get_requests(&id).unwrap(), vec![String::from(CAROL)]);
+        assert!(manager.accept_request(&id, CAROL, true).unwrap());
+        assert_eq!(manager.get_requests(&id).unwrap(), Vec::<String>::new());
+        assert_eq!(manager.get_contacts(&id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn send_interaction_returns_the_message_id() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let mut manager = daemon.manager();
+        let message_id = manager.send_interaction(&id, BOB, "hello").unwrap();
+        assert!(message_id != 0);
+        let sent = daemon.state().sent_messages.clone();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].1, BOB);
+        assert_eq!(sent[0].2.get("text/plain"), Some(&String::from("hello")));
+
+        let mut payloads = HashMap::new();
+        payloads.insert(String::from("application/geo+json"), String::from("{\"type\":\"Point\"}"));
+        manager.send_payloads(&id, BOB, payloads.clone()).unwrap();
+        assert_eq!(daemon.state().sent_messages[1].2, payloads);
+        let conversation = manager.conversation(&id, BOB).unwrap();
+        assert_eq!(conversation.interactions[1].geo_json(), Some("{\"type\":\"Point\"}"));
+    }
 
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "sendTrustMessage");
-        if !dbus_msg.is_ok() {
-            error!("sendTrustMessage fails. Please verify daemon's API.");
-            return;
+    #[test]
+    fn sent_messages_follow_their_delivery_status() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let (stop, listener) = listen(&manager);
+
+        let message_id = manager.lock().unwrap().send_interaction(&id, BOB, "hello").unwrap();
+        {
+            let m = manager.lock().unwrap();
+            let sent = &m.conversation(&id, BOB).unwrap().interactions;
+            assert_eq!(sent.len(), 1);
+            assert_eq!(sent[0].id, message_id);
+            assert_eq!(sent[0].author_ring_id, "a1b2c3");
+            assert_eq!(sent[0].peer_ring_id, BOB);
+            assert_eq!(sent[0].status, MessageStatus::Sent);
         }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return;
+        let status = |m: &Manager, index: usize| m.conversations[0].interactions[index].status;
+        daemon.emit_message_status(&id, message_id, BOB, 2);
+        assert!(wait_for(&manager, |m| status(m, 0) == MessageStatus::Delivered));
+        daemon.emit_message_status(&id, message_id, BOB, 3);
+        assert!(wait_for(&manager, |m| status(m, 0) == MessageStatus::Read));
+
+        daemon.state().fail("sendTextMessage", "org.freedesktop.DBus.Error.Failed", "no route");
+        assert!(manager.lock().unwrap().send_interaction(&id, BOB, "lost").is_err());
+        assert_eq!(status(&manager.lock().unwrap(), 1), MessageStatus::Failure);
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn history_survives_restarts() {
+        let root = env::temp_dir().join(format!("ruring-test-{}-manager", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        {
+            let mut manager = daemon.manager();
+            manager.set_history(HistoryStore::with_root(root.clone()));
+            manager.send_interaction(&id, BOB, "see you tomorrow").unwrap();
         }
-        let dbus = conn.unwrap();
-        let _ = dbus.send_with_reply_and_block(dbus_msg.unwrap().append3(from, destination, payloads), 2000);
+        let mut manager = daemon.manager();
+        assert!(manager.conversations.is_empty());
+        manager.set_history(HistoryStore::with_root(root.clone()));
+        assert_eq!(manager.conversations.len(), 1);
+        let conversation = manager.conversation(&id, BOB).unwrap();
+        assert_eq!(conversation.interactions.len(), 1);
+        assert_eq!(conversation.interactions[0].text(), Some("see you tomorrow"));
+        assert_eq!(conversation.unread(), 0);
+        assert_eq!(manager.load_history(&id, BOB), 0);
+        fs::remove_dir_all(root).unwrap();
     }
 
-    pub fn add_contact(&self, account_id: &str, contact: &str) {
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "addContact");
-        if !dbus_msg.is_ok() {
-            error!("addContact fails. Please verify daemon's API.");
-            return;
+    #[test]
+    fn older_interactions_are_loaded_by_page() {
+        let root = env::temp_dir().join(format!("ruring-test-{}-pages", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        {
+            let mut manager = daemon.manager();
+            manager.set_history(HistoryStore::with_root(root.clone()));
+            for i in 0..HISTORY_PAGE_SIZE + 10 {
+                manager.send_interaction(&id, BOB, &i.to_string()).unwrap();
+            }
         }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return;
+        let mut manager = daemon.manager();
+        manager.set_history(HistoryStore::with_root(root.clone()));
+        manager.send_interaction(&id, BOB, "new").unwrap();
+        assert_eq!(manager.conversation(&id, BOB).unwrap().interactions.len(), HISTORY_PAGE_SIZE + 1);
+        assert_eq!(manager.load_history(&id, BOB), 10);
+        assert_eq!(manager.load_history(&id, BOB), 0);
+        let interactions = &manager.conversation(&id, BOB).unwrap().interactions;
+        let texts: Vec<&str> = interactions.iter().map(|i| i.text().unwrap()).collect();
+        let mut expected: Vec<String> = (0..HISTORY_PAGE_SIZE + 10).map(|i| i.to_string()).collect();
+        expected.push(String::from("new"));
+        assert_eq!(texts, expected);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn finished_calls_are_logged() {
+        let root = env::temp_dir().join(format!("ruring-test-{}-call-log", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        manager.lock().unwrap().set_history(HistoryStore::with_root(root.clone()));
+        let (stop, listener) = listen(&manager);
+
+        let answered = manager.lock().unwrap().place_call(&id, BOB).unwrap();
+        daemon.emit_call_state(&answered, "CURRENT");
+        assert!(wait_for(&manager, |m| m.call(&answered).unwrap().state == CallState::Current));
+        manager.lock().unwrap().hang_up(&answered).unwrap();
+        assert!(wait_for(&manager, |m| m.call_log.len() == 1));
+
+        let refused = daemon.emit_incoming_call(&id, &format!("ring:{}", CAROL));
+        assert!(wait_for(&manager, |m| m.call(&refused).is_some()));
+        manager.lock().unwrap().refuse_call(&refused).unwrap();
+        assert!(wait_for(&manager, |m| m.call_log.len() == 2));
+
+        let missed = daemon.emit_incoming_call(&id, &format!("ring:{}", CAROL));
+        assert!(wait_for(&manager, |m| m.call(&missed).is_some()));
+        daemon.emit_call_state(&missed, "HUNGUP");
+        assert!(wait_for(&manager, |m| m.call_log.len() == 3));
+
+        let failed = manager.lock().unwrap().place_call(&id, DAVE).unwrap();
+        daemon.emit_call_state(&failed, "BUSY");
+        assert!(wait_for(&manager, |m| m.call_log.len() == 4));
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+
+        let mut restarted = daemon.manager();
+        restarted.set_history(HistoryStore::with_root(root.clone()));
+        let outcomes: Vec<CallOutcome> = restarted.call_log.iter().map(|record| record.outcome).collect();
+        assert_eq!(outcomes, vec![CallOutcome::Answered, CallOutcome::Refused, CallOutcome::Missed, CallOutcome::Failed]);
+        assert_eq!(restarted.call_log[0].peer, BOB);
+        assert_eq!(restarted.call_log[0].direction, Direction::Outgoing);
+        assert_eq!(restarted.call_log[1].direction, Direction::Incoming);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn contacts_are_sorted_by_activity() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let mut manager = daemon.manager();
+        for contact in &[BOB, CAROL, DAVE] {
+            manager.add_contact(&id, contact).unwrap();
         }
-        let dbus = conn.unwrap();
-        let _ = dbus.send_with_reply_and_block(dbus_msg.unwrap().append2(account_id, contact), 2000);
+        manager.send_interaction(&id, DAVE, "first").unwrap();
+        manager.send_interaction(&id, CAROL, "last").unwrap();
+        assert_eq!(manager.get_contacts_by_activity(&id).unwrap(),
+                   vec![String::from(CAROL), String::from(DAVE), String::from(BOB)]);
     }
 
-    pub fn rm_contact(&self, account_id: &str, contact: &str, banned: bool) {
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "removeContact");
-        if !dbus_msg.is_ok() {
-            error!("removeContact fails. Please verify daemon's API.");
-            return;
+    /**
+     * Wait for the answer of the name server about a name
+     * @return its status and address
+     */
+    fn name_found(events: &Receiver<RingEvent>, name: &str) -> (LookupStatus, String) {
+        loop {
+            match events.recv_timeout(Duration::from_secs(2)) {
+                Ok(RingEvent::RegisteredNameFound { status, ref address, name: ref found, .. }) if found == name =>
+                    return (status, address.clone()),
+                Ok(_) => {},
+                Err(e) => panic!("no answer for {}: {:?}", name, e),
+            }
         }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return;
+    }
+
+    #[test]
+    fn names_are_resolved_and_registered() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let bob = BOB;
+        daemon.state().names.insert(String::from("bob"), bob.to_string());
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let (stop, listener) = listen(&manager);
+
+        let events = manager.lock().unwrap().subscribe();
+        assert_eq!(manager.lock().unwrap().resolve_name(&id, bob), Ok(Some(bob.to_string())));
+        assert_eq!(manager.lock().unwrap().resolve_name(&id, "bob"), Ok(None));
+        assert_eq!(name_found(&events, "bob"), (LookupStatus::Found, bob.to_string()));
+        // Now known without asking the name server again
+        assert_eq!(manager.lock().unwrap().resolve_name(&id, "bob"), Ok(Some(bob.to_string())));
+        assert_eq!(manager.lock().unwrap().resolve_name(&id, "carol"), Ok(None));
+        assert_eq!(name_found(&events, "carol").0, LookupStatus::NotFound);
+
+        let unknown = DAVE;
+        daemon.state().names.insert(String::from("dave"), unknown.to_string());
+        assert_eq!(manager.lock().unwrap().registered_name(&id, unknown), None);
+        assert!(wait_for(&manager, |m| m.names.get(unknown) == Some(&String::from("dave"))));
+
+        assert!(manager.lock().unwrap().register_name(&id, "", "alice").unwrap());
+        assert!(wait_for(&manager, |m| m.accounts[0].registered_name == "alice"));
+        assert_eq!(daemon.state().names.get("alice"), Some(&String::from("a1b2c3")));
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn place_call_reaches_the_call_manager() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let mut manager = daemon.manager();
+        assert!(!manager.place_call(&id, BOB).unwrap().is_empty());
+        assert_eq!(daemon.state().placed_calls, vec![(id, format!("ring:{}", BOB))]);
+    }
+
+    #[test]
+    fn calls_are_tracked_through_their_lifecycle() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let (stop, listener) = listen(&manager);
+
+        // Outgoing call, answered then held, muted and hung up
+        let call_id = manager.lock().unwrap().place_call(&id, BOB).unwrap();
+        assert_eq!(manager.lock().unwrap().call(&call_id).unwrap().state, CallState::Connecting);
+        daemon.emit_call_state(&call_id, "CURRENT");
+        assert!(wait_for(&manager, |m| m.call(&call_id).unwrap().state == CallState::Current));
+        assert!(manager.lock().unwrap().call(&call_id).unwrap().start.is_some());
+        manager.lock().unwrap().hold(&call_id).unwrap();
+        assert!(wait_for(&manager, |m| m.call(&call_id).unwrap().state == CallState::Hold));
+        manager.lock().unwrap().unhold(&call_id).unwrap();
+        assert!(wait_for(&manager, |m| m.call(&call_id).unwrap().state == CallState::Current));
+        manager.lock().unwrap().mute(&call_id, true).unwrap();
+        assert!(manager.lock().unwrap().call(&call_id).unwrap().muted);
+        assert_eq!(manager.lock().unwrap().get_call_list().unwrap(), vec![call_id.clone()]);
+        manager.lock().unwrap().hang_up(&call_id).unwrap();
+        assert!(wait_for(&manager, |m| m.calls.is_empty()));
+        assert_eq!(manager.lock().unwrap().hang_up(&call_id), Err(RingError::UnknownCall(call_id.clone())));
+
+        // Incoming call, accepted then transferred
+        let incoming = daemon.emit_incoming_call(&id, &format!("ring:{}", CAROL));
+        assert!(wait_for(&manager, |m| m.call(&incoming).is_some()));
+        assert_eq!(manager.lock().unwrap().call(&incoming).unwrap().peer, CAROL);
+        manager.lock().unwrap().accept_call(&incoming).unwrap();
+        assert!(wait_for(&manager, |m| m.call(&incoming).unwrap().state == CallState::Current));
+        manager.lock().unwrap().transfer(&incoming, DAVE).unwrap();
+        assert!(wait_for(&manager, |m| m.calls.is_empty()));
+        assert_eq!(daemon.state().transfers, vec![(incoming, format!("ring:{}", DAVE))]);
+
+        // Refused call
+        let refused = daemon.emit_incoming_call(&id, &format!("ring:{}", CAROL));
+        assert!(wait_for(&manager, |m| m.call(&refused).is_some()));
+        manager.lock().unwrap().refuse_call(&refused).unwrap();
+        assert!(wait_for(&manager, |m| m.calls.is_empty()));
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn tones_are_sent_in_calls() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_sip_account("alice", "alice", "example.com");
+        let mut manager = daemon.manager();
+        let call_id = manager.place_call(&id, "sip:voicemail@example.com").unwrap();
+        assert_eq!(daemon.state().placed_calls, vec![(id, String::from("sip:voicemail@example.com"))]);
+        manager.play_dtmf(&call_id, "12 3#x*").unwrap();
+        assert_eq!(daemon.state().dtmf, "123#*");
+        assert_eq!(manager.play_dtmf("unknown", "1"), Err(RingError::UnknownCall(String::from("unknown"))));
+    }
+
+    #[test]
+    fn destinations_fit_the_account() {
+        let daemon = FakeDaemon::start();
+        let ring = daemon.state().add_account("alice", "a1b2c3");
+        let sip = daemon.state().add_sip_account("bob", "bob", "example.com");
+        let mut manager = daemon.manager();
+        manager.place_call(&ring, &BOB.to_uppercase()).unwrap();
+        manager.place_call(&sip, "+1 555-0100").unwrap();
+        manager.place_call(&sip, "carol@example.com").unwrap();
+        assert_eq!(daemon.state().placed_calls, vec![
+            (ring.clone(), format!("ring:{}", BOB)),
+            (sip.clone(), String::from("sip:+15550100")),
+            (sip.clone(), String::from("sip:carol@example.com")),
+        ]);
+        match manager.place_call(&ring, "sip:carol@example.com") {
+            Err(RingError::InvalidDestination { destination, .. }) => assert_eq!(destination, "sip:carol@example.com"),
+            other => panic!("unexpected result: {:?}", other),
         }
-        let dbus = conn.unwrap();
-        let _ = dbus.send_with_reply_and_block(dbus_msg.unwrap().append3(account_id, contact, banned), 2000);
+        assert!(manager.place_call(&sip, "").is_err());
+        assert_eq!(daemon.state().placed_calls.len(), 3);
+
+        let participants = vec![String::from("carol@example.com"), String::from("+1 555-0101")];
+        manager.create_conference(&sip, &participants).unwrap();
+        // After the three calls placed above
+        let peers: Vec<String> = daemon.state().calls.iter().skip(3)
+            .map(|call| call.1.get("PEER_NUMBER").cloned().unwrap_or_default()).collect();
+        assert_eq!(peers, vec!["sip:carol@example.com", "sip:+15550101"]);
+        assert!(manager.create_conference(&ring, &participants).is_err());
     }
 
-    pub fn get_contacts(&self, account_id: &str) -> Vec<String> {
-        let mut contacts: Vec<String> = Vec::new();
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "getContacts");
-        if !dbus_msg.is_ok() {
-            error!("getContacts fails. Please verify daemon's API.");
-            return contacts;
+    #[test]
+    fn files_are_sent_received_and_cancelled() {
+        let root = env::temp_dir().join(format!("ruring-test-{}-transfers", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let file = root.join("notes.txt");
+        fs::write(&file, "0123456789").unwrap();
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let (stop, listener) = listen(&manager);
+
+        // Sent file, followed until it is finished
+        let sent = manager.lock().unwrap().send_file(&id, BOB, &file.to_string_lossy()).unwrap();
+        assert!(wait_for(&manager, |m| m.data_transfer(sent).unwrap().status == TransferStatus::WaitPeerAcceptance));
+        {
+            let manager = manager.lock().unwrap();
+            let transfer = manager.data_transfer(sent).unwrap();
+            assert_eq!(transfer.display_name, "notes.txt");
+            assert_eq!(transfer.total_size, 10);
+            assert_eq!(transfer.direction, Direction::Outgoing);
         }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return Vec::new();
+        daemon.emit_transfer_progress(sent, 4);
+        assert!(wait_for(&manager, |m| m.data_transfer(sent).unwrap().status == TransferStatus::Ongoing));
+        assert_eq!(manager.lock().unwrap().transfer_progress(sent).unwrap(), (4, 10));
+        daemon.emit_transfer_progress(sent, 10);
+        assert!(wait_for(&manager, |m| m.data_transfer(sent).unwrap().status == TransferStatus::Finished));
+        assert_eq!(manager.lock().unwrap().data_transfer(sent).unwrap().ratio(), 1.);
+
+        // Received file, accepted into a path
+        let received = daemon.emit_incoming_transfer(&id, CAROL, "photo.jpg", 2048);
+        assert!(wait_for(&manager, |m| m.data_transfer(received).map(|t| t.status) == Some(TransferStatus::WaitHostAcceptance)));
+        let path = root.join("photo.jpg").to_string_lossy().into_owned();
+        manager.lock().unwrap().accept_transfer(received, &path).unwrap();
+        assert!(wait_for(&manager, |m| m.data_transfer(received).unwrap().status == TransferStatus::Ongoing));
+        assert_eq!(daemon.state().data_transfers[1].path, path);
+
+        // Refused file
+        let refused = daemon.emit_incoming_transfer(&id, CAROL, "virus.exe", 666);
+        assert!(wait_for(&manager, |m| m.data_transfer(refused).is_some()));
+        manager.lock().unwrap().cancel_transfer(refused).unwrap();
+        assert!(wait_for(&manager, |m| m.data_transfer(refused).unwrap().status == TransferStatus::ClosedByHost));
+        assert_eq!(manager.lock().unwrap().cancel_transfer(1234), Err(RingError::UnknownTransfer(1234)));
+
+        match manager.lock().unwrap().send_file(&id, BOB, "/nonexistent/file") {
+            Err(RingError::TransferFailed(_)) => {},
+            other => panic!("unexpected result: {:?}", other),
         }
-        let dbus = conn.unwrap();
-        let response = dbus.send_with_reply_and_block(dbus_msg.unwrap().append1(account_id), 2000).unwrap();
-        let contacts_vec: Array<Dict<&str, &str, _>, _> = match response.get1() {
-            Some(details) => details,
-            None => {
-                return contacts;
-            }
-        };
-        for details in contacts_vec {
-            for detail in details {
-                match detail {
-                    (key, value) => {
-                        if key == "id" {
-                            contacts.push(value.to_string());
-                        }
-                    }
-                }
-            }
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn conferences_follow_their_participants() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let (stop, listener) = listen(&manager);
+
+        let first = manager.lock().unwrap().place_call(&id, BOB).unwrap();
+        let second = manager.lock().unwrap().place_call(&id, CAROL).unwrap();
+        let third = manager.lock().unwrap().place_call(&id, DAVE).unwrap();
+
+        // Two calls make a conference, a third one joins it
+        manager.lock().unwrap().join_calls(&first, &second).unwrap();
+        let conference_id = manager.lock().unwrap().conferences[0].id.clone();
+        assert_eq!(manager.lock().unwrap().conferences[0].participants, vec![first.clone(), second.clone()]);
+        assert_eq!(manager.lock().unwrap().conferences[0].state, ConferenceState::Active);
+        manager.lock().unwrap().join_calls(&second, &third).unwrap();
+        assert_eq!(manager.lock().unwrap().conference_of(&third).unwrap().id, conference_id);
+
+        // Detached, the call goes on alone
+        manager.lock().unwrap().detach_call(&third).unwrap();
+        assert!(manager.lock().unwrap().conference_of(&third).is_none());
+        assert!(manager.lock().unwrap().call(&third).is_some());
+        assert_eq!(manager.lock().unwrap().detach_call(&third), Err(RingError::UnknownCall(third.clone())));
+
+        // Hanging up the conference ends its calls
+        manager.lock().unwrap().hang_up_conference(&conference_id).unwrap();
+        assert!(wait_for(&manager, |m| m.conferences.is_empty() && m.calls.len() == 1));
+        assert_eq!(manager.lock().unwrap().hang_up_conference(&conference_id),
+                   Err(RingError::UnknownConference(conference_id.clone())));
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn daemon_errors_are_reported() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = daemon.manager();
+        daemon.state().fail("getContacts", "cx.ring.Ring.Error", "no contacts for you");
+        match manager.get_contacts(&id) {
+            Err(RingError::Daemon { name, message }) => {
+                assert_eq!(name, "cx.ring.Ring.Error");
+                assert_eq!(message, "no contacts for you");
+            },
+            other => panic!("unexpected result: {:?}", other),
         }
-        contacts
     }
 
-    pub fn get_requests(&self, account_id: &str) -> Vec<String> {
-        let mut requests: Vec<String> = Vec::new();
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                "getTrustRequests");
-        if !dbus_msg.is_ok() {
-            error!("getTrustRequests fails. Please verify daemon's API.");
-            return requests;
+    #[test]
+    fn missing_daemon_is_unreachable() {
+        let daemon = FakeDaemon::start();
+        let mut manager = daemon.manager();
+        daemon.state().fail("getAccountList", "org.freedesktop.DBus.Error.ServiceUnknown", "gone");
+        match manager.get_account_list() {
+            Err(RingError::DaemonUnreachable(_)) => {},
+            other => panic!("unexpected result: {:?}", other),
         }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return Vec::new();
+        // Only the daemon is missing, the bus connection is kept for the next call
+        daemon.state().failures.clear();
+        assert!(manager.get_account_list().is_ok());
+    }
+
+    #[test]
+    fn signals_update_the_manager() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let (stop, listener) = listen(&manager);
+
+        let mut payloads = HashMap::new();
+        payloads.insert(String::from("text/plain"), String::from("hi"));
+        daemon.emit_incoming_message(&id, BOB, payloads);
+        assert!(wait_for(&manager, |m| m.conversation(&id, BOB).is_some()));
+        {
+            let mut m = manager.lock().unwrap();
+            assert_eq!(m.conversation(&id, BOB).unwrap().interactions[0].text(), Some("hi"));
+            assert_eq!(m.conversation(&id, BOB).unwrap().unread(), 1);
+            m.mark_read(&id, BOB);
+            assert_eq!(m.conversation(&id, BOB).unwrap().unread(), 0);
         }
-        let dbus = conn.unwrap();
-        let response = dbus.send_with_reply_and_block(dbus_msg.unwrap().append1(account_id), 2000).unwrap();
-        let requests_vec: Array<Dict<&str, &str, _>, _> = match response.get1() {
-            Some(details) => details,
-            None => {
-                return requests;
-            }
-        };
-        for details in requests_vec {
-            for detail in details {
-                match detail {
-                    (key, value) => {
-                        if key == "from" {
-                            requests.push(value.to_string());
-                        }
+
+        assert_eq!(manager.lock().unwrap().accounts[0].registration, RegistrationState::Registered);
+        daemon.emit_registration_state(&id, "ERROR_NETWORK", 503, "Service Unavailable");
+        assert!(wait_for(&manager, |m| m.accounts[0].registration.is_error()));
+        assert_eq!(manager.lock().unwrap().accounts[0].registration,
+                   RegistrationState::Error(RegistrationError::Network, 503, String::from("Service Unavailable")));
+        // The configuration is untouched
+        assert!(manager.lock().unwrap().accounts[0].enabled);
+
+        daemon.state().add_account("bob", BOB);
+        daemon.emit_accounts_changed();
+        assert!(wait_for(&manager, |m| m.accounts.len() == 2));
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn devices_are_linked_and_revoked() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        {
+            let mut state = daemon.state();
+            state.accounts[0].1.insert(String::from("Account.deviceID"), String::from("0002"));
+            let mut devices = HashMap::new();
+            devices.insert(String::from("0001"), String::from("laptop"));
+            devices.insert(String::from("0002"), String::from("workstation"));
+            state.devices.insert(id.clone(), devices);
+            state.passwords.insert(id.clone(), String::from("secret"));
+        }
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let events = manager.lock().unwrap().subscribe();
+        let (stop, listener) = listen(&manager);
+
+        // This device comes first
+        let names: Vec<String> = manager.lock().unwrap().accounts[0].devices.iter().map(|d| d.name.clone()).collect();
+        assert_eq!(names, vec!["workstation", "laptop"]);
+        assert!(manager.lock().unwrap().accounts[0].devices[0].current);
+
+        assert!(manager.lock().unwrap().export_on_ring(&id, "wrong").unwrap());
+        match events.recv_timeout(Duration::from_secs(2)) {
+            Ok(RingEvent::ExportOnRingEnded { status, .. }) => assert_eq!(status, ExportOnRingStatus::WrongPassword),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(manager.lock().unwrap().export_on_ring(&id, "secret").unwrap());
+        match events.recv_timeout(Duration::from_secs(2)) {
+            Ok(RingEvent::ExportOnRingEnded { account_id, status, pin }) => {
+                assert_eq!(account_id, id);
+                assert_eq!(status, ExportOnRingStatus::Success);
+                assert!(!pin.is_empty());
+            },
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        assert!(manager.lock().unwrap().revoke_device(&id, "wrong", "0001").unwrap());
+        match events.recv_timeout(Duration::from_secs(2)) {
+            Ok(RingEvent::DeviceRevocationEnded { status, .. }) => assert_eq!(status, RevocationStatus::WrongPassword),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(manager.lock().unwrap().revoke_device(&id, "secret", "0001").unwrap());
+        assert!(wait_for(&manager, |m| m.accounts[0].devices.len() == 1));
+        assert_eq!(manager.lock().unwrap().get_known_devices(&id).unwrap()[0].id, "0002");
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
+
+    /**
+     * @param events of a manager
+     * @return the events received until an AccountImportEnded, included
+     */
+    fn until_import_ended(events: &Receiver<RingEvent>) -> Vec<RingEvent> {
+        let mut received = Vec::new();
+        loop {
+            match events.recv_timeout(Duration::from_secs(2)) {
+                Ok(event) => {
+                    let ended = matches!(event, RingEvent::AccountImportEnded { .. });
+                    received.push(event);
+                    if ended {
+                        return received;
                     }
-                }
+                },
+                Err(e) => panic!("no import result: {:?}", e),
             }
         }
-        requests
     }
 
-    pub fn accept_request(&self, account_id: &str, from: &str, accept: bool) -> bool {
-        let method = if accept {"acceptTrustRequest"} else {"discardTrustRequest"};
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.configuration_path, self.configuration_iface,
-                                                method);
-        if !dbus_msg.is_ok() {
-            error!("method call fails. Please verify daemon's API.");
-            return false;
+    #[test]
+    fn accounts_are_imported_with_a_pin() {
+        let daemon = FakeDaemon::start();
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let events = manager.lock().unwrap().subscribe();
+        let (stop, listener) = listen(&manager);
+
+        let account = manager.lock().unwrap().add_account(" 0a1b2c3d ", "secret", AccountSource::Pin).unwrap();
+        assert_eq!(daemon.state().accounts[0].1.get("Account.archivePin").unwrap(), "0a1b2c3d");
+        // Still fetching the archive
+        daemon.emit_registration_state(&account.id, "INITIALIZING", 0, "");
+        daemon.emit_registration_state(&account.id, "REGISTERED", 0, "");
+        let received = until_import_ended(&events);
+        // Published after the registration change ending the import
+        match received[received.len() - 2] {
+            RingEvent::RegistrationStateChanged { ref state, .. } => assert_eq!(*state, RegistrationState::Registered),
+            ref other => panic!("unexpected event: {:?}", other),
         }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return false;
+        match received[received.len() - 1] {
+            RingEvent::AccountImportEnded { ref account_id, ref registration } => {
+                assert_eq!(*account_id, account.id);
+                assert_eq!(*registration, RegistrationState::Registered);
+            },
+            ref other => panic!("unexpected event: {:?}", other),
         }
-        let dbus = conn.unwrap();
-        let response = dbus.send_with_reply_and_block(
-            dbus_msg.unwrap().append3(account_id, from, accept), 2000).unwrap();
-        match response.get1() {
-            Some(result) => {
-                return result;
+
+        // A wrong PIN or password
+        let account = manager.lock().unwrap().add_account("0a1b2c3d", "wrong", AccountSource::Pin).unwrap();
+        daemon.emit_registration_state(&account.id, "ERROR_GENERIC", 0, "");
+        match until_import_ended(&events).pop() {
+            Some(RingEvent::AccountImportEnded { account_id, registration }) => {
+                assert_eq!(account_id, account.id);
+                assert!(registration.is_error());
             },
-            None => {
-                return false;
+            other => panic!("unexpected event: {:?}", other),
+        }
+        // Already reported
+        daemon.emit_registration_state(&account.id, "REGISTERED", 0, "");
+        loop {
+            match events.recv_timeout(Duration::from_millis(500)) {
+                Ok(RingEvent::AccountImportEnded { .. }) => panic!("import reported twice"),
+                Ok(_) => {},
+                Err(_) => break,
             }
-        };
+        }
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
     }
 
-    pub fn place_call(&self, account_id: &str, destination: &str) -> String {
-        let dbus_msg = Message::new_method_call(self.ring_dbus, self.call_path, self.call_iface,
-                                                "placeCall");
-        if !dbus_msg.is_ok() {
-            error!("placeCall call fails. Please verify daemon's API.");
-            return String::new();
-        }
-        let conn = Connection::get_private(BusType::Session);
-        if !conn.is_ok() {
-            return String::new();
+    #[test]
+    fn subscribers_receive_events() {
+        let daemon = FakeDaemon::start();
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let events = manager.lock().unwrap().subscribe();
+        let (stop, listener) = listen(&manager);
+
+        daemon.emit_trust_request(&id, CAROL);
+        match events.recv_timeout(Duration::from_secs(2)) {
+            Ok(RingEvent::IncomingTrustRequest { account_id, from, .. }) => {
+                assert_eq!(account_id, id);
+                assert_eq!(from, CAROL);
+            },
+            other => panic!("unexpected event: {:?}", other),
         }
-        let dbus = conn.unwrap();
-        let response = dbus.send_with_reply_and_block(
-            dbus_msg.unwrap().append2(account_id, format!("ring:{}", destination)), 2000).unwrap();
-        match response.get1() {
-            Some(result) => {
-                return result;
+
+        daemon.emit_registration_state(&id, "ERROR_AUTH", 401, "Unauthorized");
+        match events.recv_timeout(Duration::from_secs(2)) {
+            Ok(RingEvent::RegistrationStateChanged { state, .. }) => {
+                assert_eq!(state, RegistrationState::Error(RegistrationError::Auth, 401, String::from("Unauthorized")));
             },
-            None => {
-                return String::new();
-            }
-        };
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
     }
 
+    #[test]
+    fn jami_daemon_is_found_on_the_bus() {
+        let daemon = FakeDaemon::start_with_names(DaemonNames::jami());
+        let id = daemon.state().add_account("alice", "a1b2c3");
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        assert_eq!(manager.lock().unwrap().accounts.len(), 1);
+        assert!(manager.lock().unwrap().place_call(&id, BOB).is_ok());
+
+        let (stop, listener) = listen(&manager);
+        daemon.state().add_account("bob", BOB);
+        daemon.emit_accounts_changed();
+        assert!(wait_for(&manager, |m| m.accounts.len() == 2));
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn handle_signals_stops_on_request() {
+        let daemon = FakeDaemon::start();
+        let manager = Arc::new(Mutex::new(daemon.manager()));
+        let (stop, listener) = listen(&manager);
+        stop.store(true, Ordering::SeqCst);
+        listener.join().unwrap();
+    }
 }
@@ -0,0 +1,497 @@
+use ring::api::transfer::{DataTransferInfo, Transfer, TransferStatus, TRANSFER_SUCCESS, TRANSFER_UNKNOWN};
+use ring::daemon::RingDaemon;
+use ring::error::RingError;
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/**
+ * What the in-memory daemon knows
+ */
+pub struct MemoryState {
+    // (accountId, details), in creation order
+    pub accounts: Vec<(String, HashMap<String, String>)>,
+    // accountId -> contacts
+    pub contacts: HashMap<String, Vec<String>>,
+    // accountId -> pending trust requests
+    pub requests: HashMap<String, Vec<String>>,
+    // (accountId, destination, payloads) for each sent message
+    pub sent_messages: Vec<(String, String, HashMap<String, String>)>,
+    // (accountId, destination) for each placed call
+    pub placed_calls: Vec<(String, String)>,
+    // (callId, details) of the current calls
+    pub calls: Vec<(String, HashMap<String, String>)>,
+    // (conferenceId, callIds) of the current conferences
+    pub conferences: Vec<(String, Vec<String>)>,
+    // tones played, in order
+    pub dtmf: String,
+    pub data_transfers: Vec<Transfer>,
+    next_id: u64,
+}
+
+impl MemoryState {
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn account_mut(&mut self, account_id: &str) -> Result<&mut HashMap<String, String>, RingError> {
+        match self.accounts.iter_mut().find(|a| a.0 == account_id) {
+            Some(account) => Ok(&mut account.1),
+            None => Err(unknown_account(account_id)),
+        }
+    }
+
+    /**
+     * Change a detail of a call
+     * @return false if the call is unknown
+     */
+    fn set_call_detail(&mut self, call_id: &str, key: &str, value: &str) -> bool {
+        match self.calls.iter_mut().find(|c| c.0 == call_id) {
+            Some(call) => {
+                call.1.insert(key.to_string(), value.to_string());
+                true
+            },
+            None => false,
+        }
+    }
+
+    /**
+     * @return false if the call is unknown
+     */
+    fn end_call(&mut self, call_id: &str) -> bool {
+        let count = self.calls.len();
+        self.calls.retain(|c| c.0 != call_id);
+        self.detach(call_id);
+        count != self.calls.len()
+    }
+
+    /**
+     * Take a call out of its conference, removing conferences left with one call
+     * @return false if the call wasn't in a conference
+     */
+    fn detach(&mut self, call_id: &str) -> bool {
+        let mut found = false;
+        for conference in self.conferences.iter_mut() {
+            let count = conference.1.len();
+            conference.1.retain(|c| c != call_id);
+            found = found || count != conference.1.len();
+        }
+        self.conferences.retain(|c| c.1.len() > 1);
+        found
+    }
+
+    fn check_account(&self, account_id: &str) -> Result<(), RingError> {
+        if self.accounts.iter().any(|a| a.0 == account_id) {
+            Ok(())
+        } else {
+            Err(unknown_account(account_id))
+        }
+    }
+}
+
+/**
+ * A daemon without any network, keeping everything in memory.
+ * Used for offline demos and tests.
+ */
+pub struct MemoryDaemon {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryDaemon {
+    pub fn new() -> MemoryDaemon {
+        MemoryDaemon {
+            state: Mutex::new(MemoryState {
+                accounts: Vec::new(),
+                contacts: HashMap::new(),
+                requests: HashMap::new(),
+                sent_messages: Vec::new(),
+                placed_calls: Vec::new(),
+                calls: Vec::new(),
+                conferences: Vec::new(),
+                dtmf: String::new(),
+                data_transfers: Vec::new(),
+                next_id: 1,
+            })
+        }
+    }
+
+    /**
+     * Access the state of the daemon
+     */
+    pub fn state(&self) -> MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap()
+    }
+}
+
+fn unknown_account(account_id: &str) -> RingError {
+    RingError::Daemon {
+        name: String::from("org.freedesktop.DBus.Error.InvalidArgs"),
+        message: format!("Unknown account {}", account_id),
+    }
+}
+
+impl RingDaemon for MemoryDaemon {
+    fn get_account_list(&self) -> Result<Vec<String>, RingError> {
+        Ok(self.state().accounts.iter().map(|a| a.0.clone()).collect())
+    }
+
+    fn get_account_details(&self, account_id: &str) -> Result<HashMap<String, String>, RingError> {
+        Ok(self.state().account_mut(account_id)?.clone())
+    }
+
+    fn get_volatile_account_details(&self, account_id: &str) -> Result<HashMap<String, String>, RingError> {
+        // Without network, enabled accounts are always registered
+        let enabled = self.state().account_mut(account_id)?.get("Account.enable").map(|e| e == "true");
+        let status = if enabled.unwrap_or(false) {"REGISTERED"} else {"UNREGISTERED"};
+        let mut details = HashMap::new();
+        details.insert(String::from("Account.registrationStatus"), status.to_string());
+        Ok(details)
+    }
+
+    fn set_account_details(&self, account_id: &str, details: HashMap<String, String>) -> Result<(), RingError> {
+        *self.state().account_mut(account_id)? = details;
+        Ok(())
+    }
+
+    fn add_account(&self, mut details: HashMap<String, String>) -> Result<String, RingError> {
+        let mut state = self.state();
+        let id = format!("{:016x}", state.next_id());
+        // Generate a ring id, like the daemon does for new accounts
+        let ring_id = format!("{:040x}", state.next_id());
+        details.entry(String::from("Account.username")).or_insert(ring_id);
+        details.entry(String::from("Account.enable")).or_insert(String::from("true"));
+        details.remove("Account.archivePassword");
+        state.accounts.push((id.clone(), details));
+        Ok(id)
+    }
+
+    fn rm_account(&self, account_id: &str) -> Result<(), RingError> {
+        let mut state = self.state();
+        state.check_account(account_id)?;
+        state.accounts.retain(|a| a.0 != account_id);
+        state.contacts.remove(account_id);
+        state.requests.remove(account_id);
+        Ok(())
+    }
+
+    fn enable_account(&self, account_id: &str, enable: bool) -> Result<(), RingError> {
+        self.state().account_mut(account_id)?
+                    .insert(String::from("Account.enable"), format!("{}", enable));
+        Ok(())
+    }
+
+    // Offline accounts have no archive
+
+    fn export_to_file(&self, account_id: &str, _path: &str, _password: &str) -> Result<bool, RingError> {
+        self.state().check_account(account_id)?;
+        Ok(false)
+    }
+
+    fn change_account_password(&self, account_id: &str, _old_password: &str, _new_password: &str) -> Result<bool, RingError> {
+        self.state().check_account(account_id)?;
+        Ok(false)
+    }
+
+    fn get_contacts(&self, account_id: &str) -> Result<Vec<String>, RingError> {
+        let state = self.state();
+        state.check_account(account_id)?;
+        Ok(state.contacts.get(account_id).cloned().unwrap_or(Vec::new()))
+    }
+
+    fn add_contact(&self, account_id: &str, contact: &str) -> Result<(), RingError> {
+        let mut state = self.state();
+        state.check_account(account_id)?;
+        let contacts = state.contacts.entry(account_id.to_string()).or_default();
+        if !contacts.iter().any(|c| c == contact) {
+            contacts.push(contact.to_string());
+        }
+        Ok(())
+    }
+
+    fn rm_contact(&self, account_id: &str, contact: &str, _banned: bool) -> Result<(), RingError> {
+        let mut state = self.state();
+        state.check_account(account_id)?;
+        if let Some(contacts) = state.contacts.get_mut(account_id) {
+            contacts.retain(|c| c != contact);
+        }
+        Ok(())
+    }
+
+    fn get_requests(&self, account_id: &str) -> Result<Vec<String>, RingError> {
+        let state = self.state();
+        state.check_account(account_id)?;
+        Ok(state.requests.get(account_id).cloned().unwrap_or(Vec::new()))
+    }
+
+    fn send_trust_request(&self, from: &str, _destination: &str) -> Result<(), RingError> {
+        self.state().check_account(from)
+    }
+
+    fn accept_request(&self, account_id: &str, from: &str, accept: bool) -> Result<bool, RingError> {
+        let mut state = self.state();
+        state.check_account(account_id)?;
+        let found = match state.requests.get_mut(account_id) {
+            Some(requests) => {
+                let found = requests.iter().any(|r| r == from);
+                requests.retain(|r| r != from);
+                found
+            },
+            None => false
+        };
+        if found && accept {
+            state.contacts.entry(account_id.to_string()).or_default().push(from.to_string());
+        }
+        Ok(found)
+    }
+
+    fn send_text_message(&self, from: &str, destination: &str,
+                         payloads: HashMap<String, String>) -> Result<u64, RingError> {
+        let mut state = self.state();
+        state.check_account(from)?;
+        state.sent_messages.push((from.to_string(), destination.to_string(), payloads));
+        Ok(state.next_id())
+    }
+
+    // There is no name server offline, so lookups never start
+
+    fn lookup_name(&self, account_id: &str, _name_server: &str, _name: &str) -> Result<bool, RingError> {
+        self.state().check_account(account_id)?;
+        Ok(false)
+    }
+
+    fn lookup_address(&self, account_id: &str, _name_server: &str, _address: &str) -> Result<bool, RingError> {
+        self.state().check_account(account_id)?;
+        Ok(false)
+    }
+
+    fn register_name(&self, account_id: &str, _password: &str, _name: &str) -> Result<bool, RingError> {
+        self.state().check_account(account_id)?;
+        Ok(false)
+    }
+
+    fn get_known_ring_devices(&self, account_id: &str) -> Result<HashMap<String, String>, RingError> {
+        let mut state = self.state();
+        let details = state.account_mut(account_id)?;
+        // Only this device can be known offline
+        let mut devices = HashMap::new();
+        if let Some(id) = details.get("Account.deviceID") {
+            devices.insert(id.clone(), details.get("Account.deviceName").cloned().unwrap_or(String::new()));
+        }
+        Ok(devices)
+    }
+
+    fn revoke_device(&self, account_id: &str, _password: &str, _device_id: &str) -> Result<bool, RingError> {
+        self.state().check_account(account_id)?;
+        Ok(false)
+    }
+
+    fn export_on_ring(&self, account_id: &str, _password: &str) -> Result<bool, RingError> {
+        self.state().check_account(account_id)?;
+        Ok(false)
+    }
+
+    fn place_call(&self, account_id: &str, destination: &str) -> Result<String, RingError> {
+        let mut state = self.state();
+        state.check_account(account_id)?;
+        state.placed_calls.push((account_id.to_string(), destination.to_string()));
+        let call_id = format!("{}", state.next_id());
+        // Nobody answers offline
+        let mut details = HashMap::new();
+        details.insert(String::from("ACCOUNTID"), account_id.to_string());
+        details.insert(String::from("PEER_NUMBER"), destination.to_string());
+        details.insert(String::from("CALL_TYPE"), String::from("1"));
+        details.insert(String::from("CALL_STATE"), String::from("CONNECTING"));
+        state.calls.push((call_id.clone(), details));
+        Ok(call_id)
+    }
+
+    fn accept(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.state().set_call_detail(call_id, "CALL_STATE", "CURRENT"))
+    }
+
+    fn refuse(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.state().end_call(call_id))
+    }
+
+    fn hang_up(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.state().end_call(call_id))
+    }
+
+    fn hold(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.state().set_call_detail(call_id, "CALL_STATE", "HOLD"))
+    }
+
+    fn unhold(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.state().set_call_detail(call_id, "CALL_STATE", "CURRENT"))
+    }
+
+    fn mute_local_media(&self, call_id: &str, media_type: &str, mute: bool) -> Result<bool, RingError> {
+        let key = format!("{}_MUTED", media_type);
+        Ok(self.state().set_call_detail(call_id, &key, if mute {"true"} else {"false"}))
+    }
+
+    fn transfer(&self, call_id: &str, _to: &str) -> Result<bool, RingError> {
+        Ok(self.state().end_call(call_id))
+    }
+
+    fn play_dtmf(&self, key: &str) -> Result<(), RingError> {
+        self.state().dtmf += key;
+        Ok(())
+    }
+
+    fn get_call_list(&self) -> Result<Vec<String>, RingError> {
+        Ok(self.state().calls.iter().map(|c| c.0.clone()).collect())
+    }
+
+    fn get_call_details(&self, call_id: &str) -> Result<HashMap<String, String>, RingError> {
+        Ok(self.state().calls.iter().find(|c| c.0 == call_id).map(|c| c.1.clone()).unwrap_or(HashMap::new()))
+    }
+
+    fn join_participant(&self, sel_call_id: &str, drag_call_id: &str) -> Result<bool, RingError> {
+        let mut state = self.state();
+        if !state.calls.iter().any(|c| c.0 == sel_call_id) || !state.calls.iter().any(|c| c.0 == drag_call_id) {
+            return Ok(false);
+        }
+        state.detach(drag_call_id);
+        let index = state.conferences.iter().position(|c| c.1.iter().any(|p| p == sel_call_id));
+        match index {
+            Some(index) => state.conferences[index].1.push(drag_call_id.to_string()),
+            None => {
+                let conference_id = format!("{}", state.next_id());
+                state.conferences.push((conference_id, vec![sel_call_id.to_string(), drag_call_id.to_string()]));
+            }
+        }
+        Ok(true)
+    }
+
+    fn create_conf_from_participant_list(&self, participants: Vec<String>) -> Result<(), RingError> {
+        // Nobody answers offline, so there is nobody to talk with
+        warn!("Can't call {:?} offline", participants);
+        Ok(())
+    }
+
+    fn add_participant(&self, call_id: &str, conference_id: &str) -> Result<bool, RingError> {
+        let mut state = self.state();
+        if !state.calls.iter().any(|c| c.0 == call_id) || !state.conferences.iter().any(|c| c.0 == conference_id) {
+            return Ok(false);
+        }
+        state.detach(call_id);
+        match state.conferences.iter_mut().find(|c| c.0 == conference_id) {
+            Some(conference) => {
+                conference.1.push(call_id.to_string());
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    fn detach_participant(&self, call_id: &str) -> Result<bool, RingError> {
+        Ok(self.state().detach(call_id))
+    }
+
+    fn hang_up_conference(&self, conference_id: &str) -> Result<bool, RingError> {
+        let mut state = self.state();
+        let participants = match state.conferences.iter().find(|c| c.0 == conference_id) {
+            Some(conference) => conference.1.clone(),
+            None => return Ok(false),
+        };
+        for call_id in participants {
+            state.end_call(&call_id);
+        }
+        Ok(true)
+    }
+
+    fn get_conference_list(&self) -> Result<Vec<String>, RingError> {
+        Ok(self.state().conferences.iter().map(|c| c.0.clone()).collect())
+    }
+
+    fn get_participant_list(&self, conference_id: &str) -> Result<Vec<String>, RingError> {
+        Ok(self.state().conferences.iter().find(|c| c.0 == conference_id).map(|c| c.1.clone()).unwrap_or(Vec::new()))
+    }
+
+    fn get_conference_details(&self, conference_id: &str) -> Result<HashMap<String, String>, RingError> {
+        let mut details = HashMap::new();
+        if self.state().conferences.iter().any(|c| c.0 == conference_id) {
+            details.insert(String::from("CONFID"), conference_id.to_string());
+            details.insert(String::from("CONF_STATE"), String::from("ACTIVE_ATTACHED"));
+        }
+        Ok(details)
+    }
+
+    fn data_transfer_list(&self) -> Result<Vec<u64>, RingError> {
+        Ok(self.state().data_transfers.iter().map(|t| t.id).collect())
+    }
+
+    fn send_file(&self, info: DataTransferInfo) -> Result<(u32, u64), RingError> {
+        let mut state = self.state();
+        state.check_account(&info.0)?;
+        let id = state.next_id();
+        // Nobody accepts offline
+        let mut transfer = Transfer::from_info(id, info);
+        transfer.status = TransferStatus::WaitPeerAcceptance;
+        state.data_transfers.push(transfer);
+        Ok((TRANSFER_SUCCESS, id))
+    }
+
+    fn data_transfer_info(&self, id: u64) -> Result<(u32, DataTransferInfo), RingError> {
+        match self.state().data_transfers.iter().find(|t| t.id == id) {
+            Some(transfer) => Ok((TRANSFER_SUCCESS, transfer.to_info())),
+            None => Ok((TRANSFER_UNKNOWN, Transfer::empty_info())),
+        }
+    }
+
+    fn data_transfer_bytes_progress(&self, id: u64) -> Result<(u32, i64, i64), RingError> {
+        match self.state().data_transfers.iter().find(|t| t.id == id) {
+            Some(transfer) => Ok((TRANSFER_SUCCESS, transfer.total_size, transfer.progress)),
+            None => Ok((TRANSFER_UNKNOWN, 0, 0)),
+        }
+    }
+
+    fn accept_file_transfer(&self, id: u64, file_path: &str, _offset: i64) -> Result<u32, RingError> {
+        match self.state().data_transfers.iter_mut().find(|t| t.id == id) {
+            Some(transfer) => {
+                transfer.path = file_path.to_string();
+                transfer.status = TransferStatus::Ongoing;
+                Ok(TRANSFER_SUCCESS)
+            },
+            None => Ok(TRANSFER_UNKNOWN),
+        }
+    }
+
+    fn cancel_data_transfer(&self, id: u64) -> Result<u32, RingError> {
+        match self.state().data_transfers.iter_mut().find(|t| t.id == id) {
+            Some(transfer) => {
+                transfer.status = TransferStatus::ClosedByHost;
+                Ok(TRANSFER_SUCCESS)
+            },
+            None => Ok(TRANSFER_UNKNOWN),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::api::account::AccountSource;
+    use ring::manager::Manager;
+    use ring::memory_daemon::MemoryDaemon;
+
+    #[test]
+    fn manager_runs_without_a_daemon() {
+        let mut manager = Manager::with_daemon(Box::new(MemoryDaemon::new())).unwrap();
+        let account = manager.add_account("alice", "", AccountSource::New).unwrap();
+        assert_eq!(account.alias, "alice");
+        assert_eq!(account.ring_id.len(), 40);
+        manager.add_contact(&account.id, "d4e5f6").unwrap();
+        assert_eq!(manager.get_contacts(&account.id).unwrap(), vec![String::from("d4e5f6")]);
+        assert!(manager.send_interaction(&account.id, "d4e5f6", "hello").is_ok());
+    }
+
+    #[test]
+    fn unknown_accounts_are_errors() {
+        let mut manager = Manager::with_daemon(Box::new(MemoryDaemon::new())).unwrap();
+        assert!(manager.get_contacts("nope").is_err());
+        assert!(manager.place_call("nope", "d4e5f6").is_err());
+    }
+}
@@ -1,2 +1,11 @@
 pub mod api;
+pub mod connection;
+pub mod daemon;
+pub mod dbus_daemon;
+pub mod error;
+#[cfg(test)]
+pub mod fake_daemon;
+pub mod history;
 pub mod manager;
+pub mod memory_daemon;
+pub mod signals;
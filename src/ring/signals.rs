@@ -0,0 +1,296 @@
+use ring::api::account::RegistrationState;
+use ring::api::call::CallState;
+use ring::api::conference::ConferenceState;
+use ring::api::device::{ExportOnRingStatus, RevocationStatus};
+use ring::api::event::RingEvent;
+use ring::api::interaction::{Direction, Interaction, MessageStatus};
+use ring::api::name::{LookupStatus, NameRegistrationState};
+use ring::api::transfer::TransferStatus;
+use ring::daemon::EventSource;
+use ring::dbus_daemon::DaemonNames;
+use ring::error::RingError;
+
+use dbus::{Connection, ConnectionItem, Message};
+use dbus::arg::Dict;
+use time;
+
+/**
+ * Receives the signals of a D-Bus daemon and transforms them into events
+ */
+pub struct SignalListener {
+    conn: Connection,
+    names: DaemonNames,
+}
+
+impl SignalListener {
+    /**
+     * Subscribe to the interresting signals of the daemon
+     * @param conn a connection dedicated to signals
+     * @param names of the daemon
+     */
+    pub fn new(conn: Connection, names: DaemonNames) -> Result<SignalListener, RingError> {
+        let members = ["incomingAccountMessage", "incomingTrustRequest",
+                       "accountsChanged", "registrationStateChanged",
+                       "accountMessageStatusChanged", "registeredNameFound",
+                       "nameRegistrationEnded", "dataTransferEvent", "knownDevicesChanged",
+                       "deviceRevocationEnded", "exportOnRingEnded"];
+        for member in members.iter() {
+            conn.add_match(&format!("interface={},member={}", names.configuration_iface, member))?;
+        }
+        let call_members = ["incomingCall", "callStateChanged", "conferenceCreated",
+                            "conferenceChanged", "conferenceRemoved"];
+        for member in call_members.iter() {
+            conn.add_match(&format!("interface={},member={}", names.call_iface, member))?;
+        }
+        Ok(SignalListener {
+            conn,
+            names,
+        })
+    }
+
+    /**
+     * Transform a daemon's signal into an event
+     * @param self
+     * @param msg the signal
+     * @return the event, if the signal is interresting
+     */
+    fn parse_signal(&self, msg: &Message) -> Option<RingEvent> {
+        let iface = msg.interface()?;
+        if *iface == *self.names.call_iface {
+            return match &*msg.member()? {
+                "incomingCall" => SignalListener::parse_incoming_call(msg),
+                "callStateChanged" => SignalListener::parse_call_state(msg),
+                "conferenceCreated" => Some(RingEvent::ConferenceCreated),
+                "conferenceChanged" => SignalListener::parse_conference_changed(msg),
+                "conferenceRemoved" => Some(RingEvent::ConferenceRemoved {
+                    conference_id: msg.get1::<&str>()?.to_string(),
+                }),
+                _ => None
+            };
+        }
+        if *iface != *self.names.configuration_iface { return None };
+        match &*msg.member()? {
+            "incomingAccountMessage" => SignalListener::parse_interaction(msg),
+            "incomingTrustRequest" => SignalListener::parse_request(msg),
+            "accountsChanged" => Some(RingEvent::AccountsChanged),
+            "registrationStateChanged" => SignalListener::parse_registration_changed(msg),
+            "accountMessageStatusChanged" => SignalListener::parse_message_status(msg),
+            "registeredNameFound" => SignalListener::parse_name_found(msg),
+            "nameRegistrationEnded" => SignalListener::parse_name_registration(msg),
+            "dataTransferEvent" => SignalListener::parse_transfer_event(msg),
+            "knownDevicesChanged" => SignalListener::parse_devices_changed(msg),
+            "deviceRevocationEnded" => SignalListener::parse_revocation(msg),
+            "exportOnRingEnded" => SignalListener::parse_export_on_ring(msg),
+            _ => None
+        }
+    }
+
+    /**
+     * Handle new interactions signals
+     * @param msg
+     * @return the IncomingMessage event
+     */
+    fn parse_interaction(msg: &Message) -> Option<RingEvent> {
+        // incomingAccountMessage return three arguments
+        let (account_id, author_ring_id, payloads) = msg.get3::<&str, &str, Dict<&str, &str, _>>();
+        let author_ring_id = author_ring_id?.to_string();
+        let payloads = payloads?.map(|(key, value)| (key.to_string(), value.to_string())).collect();
+        let interaction = Interaction {
+            id: 0,
+            author_ring_id: author_ring_id.clone(),
+            peer_ring_id: author_ring_id,
+            payloads,
+            time: time::now(),
+            direction: Direction::Incoming,
+            status: MessageStatus::Delivered,
+        };
+        Some(RingEvent::IncomingMessage {
+            account_id: account_id?.to_string(),
+            interaction,
+        })
+    }
+
+    /**
+     * Handle delivery status changes of sent messages
+     * @param msg
+     * @return the MessageStatusChanged event, None for unknown statuses
+     */
+    fn parse_message_status(msg: &Message) -> Option<RingEvent> {
+        // accountMessageStatusChanged return four arguments, message ids being unique to the account
+        let (account_id, message_id, _, status) = msg.get4::<&str, u64, &str, i32>();
+        Some(RingEvent::MessageStatusChanged {
+            account_id: account_id?.to_string(),
+            message_id: message_id?,
+            status: MessageStatus::from_daemon(status?)?,
+        })
+    }
+
+    /**
+     * Handle new incoming calls
+     * @param msg
+     * @return the IncomingCall event
+     */
+    fn parse_incoming_call(msg: &Message) -> Option<RingEvent> {
+        let (account_id, call_id, from) = msg.get3::<&str, &str, &str>();
+        Some(RingEvent::IncomingCall {
+            account_id: account_id?.to_string(),
+            call_id: call_id?.to_string(),
+            from: from.unwrap_or("").to_string(),
+        })
+    }
+
+    /**
+     * Handle call changes
+     * @param msg
+     * @return the CallStateChanged event
+     */
+    fn parse_call_state(msg: &Message) -> Option<RingEvent> {
+        let (call_id, state) = msg.get2::<&str, &str>();
+        Some(RingEvent::CallStateChanged {
+            call_id: call_id?.to_string(),
+            state: CallState::from_daemon(state?),
+        })
+    }
+
+    /**
+     * Handle file transfer changes
+     * @param msg
+     * @return the DataTransferEvent event
+     */
+    fn parse_transfer_event(msg: &Message) -> Option<RingEvent> {
+        let (transfer_id, code) = msg.get2::<u64, i32>();
+        Some(RingEvent::DataTransferEvent {
+            transfer_id: transfer_id?,
+            status: TransferStatus::from_daemon(code? as u32),
+        })
+    }
+
+    /**
+     * Handle devices linked or unlinked
+     * @param msg
+     * @return the KnownDevicesChanged event
+     */
+    fn parse_devices_changed(msg: &Message) -> Option<RingEvent> {
+        let (account_id, devices) = msg.get2::<&str, Dict<&str, &str, _>>();
+        Some(RingEvent::KnownDevicesChanged {
+            account_id: account_id?.to_string(),
+            devices: devices?.map(|(id, name)| (id.to_string(), name.to_string())).collect(),
+        })
+    }
+
+    /**
+     * Handle the end of a revokeDevice
+     * @param msg
+     * @return the DeviceRevocationEnded event
+     */
+    fn parse_revocation(msg: &Message) -> Option<RingEvent> {
+        let (account_id, device_id, status) = msg.get3::<&str, &str, i32>();
+        Some(RingEvent::DeviceRevocationEnded {
+            account_id: account_id?.to_string(),
+            device_id: device_id?.to_string(),
+            status: RevocationStatus::from_daemon(status?),
+        })
+    }
+
+    /**
+     * Handle the end of an exportOnRing
+     * @param msg
+     * @return the ExportOnRingEnded event
+     */
+    fn parse_export_on_ring(msg: &Message) -> Option<RingEvent> {
+        let (account_id, status, pin) = msg.get3::<&str, i32, &str>();
+        Some(RingEvent::ExportOnRingEnded {
+            account_id: account_id?.to_string(),
+            status: ExportOnRingStatus::from_daemon(status?),
+            pin: pin.unwrap_or("").to_string(),
+        })
+    }
+
+    /**
+     * Handle conference changes
+     * @param msg
+     * @return the ConferenceChanged event
+     */
+    fn parse_conference_changed(msg: &Message) -> Option<RingEvent> {
+        let (conference_id, state) = msg.get2::<&str, &str>();
+        Some(RingEvent::ConferenceChanged {
+            conference_id: conference_id?.to_string(),
+            state: ConferenceState::from_daemon(state?),
+        })
+    }
+
+    /**
+     * Handle name server answers
+     * @param msg
+     * @return the RegisteredNameFound event
+     */
+    fn parse_name_found(msg: &Message) -> Option<RingEvent> {
+        // registeredNameFound return four arguments
+        let (account_id, status, address, name) = msg.get4::<&str, i32, &str, &str>();
+        Some(RingEvent::RegisteredNameFound {
+            account_id: account_id?.to_string(),
+            status: LookupStatus::from_daemon(status?),
+            address: address.unwrap_or("").to_string(),
+            name: name.unwrap_or("").to_string(),
+        })
+    }
+
+    /**
+     * Handle the end of name registrations
+     * @param msg
+     * @return the NameRegistrationEnded event
+     */
+    fn parse_name_registration(msg: &Message) -> Option<RingEvent> {
+        let (account_id, state, name) = msg.get3::<&str, i32, &str>();
+        Some(RingEvent::NameRegistrationEnded {
+            account_id: account_id?.to_string(),
+            state: NameRegistrationState::from_daemon(state?),
+            name: name.unwrap_or("").to_string(),
+        })
+    }
+
+    /**
+     * Handle registration changes signals
+     * @param msg
+     * @return the RegistrationStateChanged event
+     */
+    fn parse_registration_changed(msg: &Message) -> Option<RingEvent> {
+        let (account_id, registration_state, code, detail) = msg.get4::<&str, &str, i32, &str>();
+        Some(RingEvent::RegistrationStateChanged {
+            account_id: account_id?.to_string(),
+            state: RegistrationState::from_daemon(registration_state?, code.unwrap_or(0), detail.unwrap_or("")),
+        })
+    }
+
+    /**
+     * Handle new pending requests signals
+     * @param msg
+     * @return the IncomingTrustRequest event
+     */
+    fn parse_request(msg: &Message) -> Option<RingEvent> {
+        // incomingTrustRequest also gives the payload of the request and when it was received
+        let (account_id, from) = msg.get2::<&str, &str>();
+        Some(RingEvent::IncomingTrustRequest {
+            account_id: account_id?.to_string(),
+            from: from?.to_string(),
+        })
+    }
+}
+
+impl EventSource for SignalListener {
+    fn poll(&mut self, timeout_ms: i32) -> Vec<RingEvent> {
+        let mut events = Vec::new();
+        for item in self.conn.iter(timeout_ms) {
+            match item {
+                ConnectionItem::Signal(ref signal) => {
+                    if let Some(event) = self.parse_signal(signal) {
+                        events.push(event);
+                    }
+                },
+                ConnectionItem::Nothing => break,
+                _ => {}
+            }
+        }
+        events
+    }
+}
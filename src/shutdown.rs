@@ -0,0 +1,64 @@
+use libc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set from the signal handlers, which can't reach any Shutdown.
+static SIGNALED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_signal: libc::c_int) {
+    SIGNALED.store(true, Ordering::SeqCst);
+}
+
+/**
+ * Shared between threads to ask all of them to quit
+ */
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    pub fn new() -> Shutdown {
+        Shutdown {
+            requested: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /**
+     * Request the shutdown on SIGINT and SIGTERM
+     * @param self
+     */
+    pub fn install_signal_handlers(&self) {
+        unsafe {
+            libc::signal(libc::SIGINT, on_signal as *const () as libc::sighandler_t);
+            libc::signal(libc::SIGTERM, on_signal as *const () as libc::sighandler_t);
+        }
+    }
+
+    /**
+     * Ask every thread to quit
+     * @param self
+     */
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    /**
+     * @param self
+     * @return if threads should quit
+     */
+    pub fn is_requested(&self) -> bool {
+        if SIGNALED.load(Ordering::SeqCst) {
+            self.request();
+        }
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /**
+     * @param self
+     * @return the flag set when the shutdown is requested
+     */
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.requested.clone()
+    }
+}
@@ -1,12 +1,31 @@
 use ncurses::*;
-use ring::manager::Manager;
-use ring::api::interaction::Interaction;
+use ring::manager::{is_dtmf, Manager};
+use ring::api::account::AccountSource;
+use ring::api::account_details::{editable_details, DetailKind, SipSettings, SipTransport};
+use ring::api::call::{CallOutcome, CallState};
+use ring::api::device::ExportOnRingStatus;
+use ring::api::event::RingEvent;
+use ring::api::interaction::{Direction, Interaction, MessageStatus};
+use ring::api::name::{LookupStatus, NameRegistrationState};
+use ring::api::transfer::TransferStatus;
+use ring::error::RingError;
+use shutdown::Shutdown;
 use std;
 use std::sync::{Arc, Mutex};
 
-static COLOR_BACKGROUND: i16 = 16;
-static COLOR_KEYWORD: i16 = 18;
 static COLOR_SELECTED: i16 = 2;
+static COLOR_FAILED: i16 = 3;
+
+/**
+ * Restores the terminal when dropped, even while panicking
+ */
+struct Terminal;
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        endwin();
+    }
+}
 
 pub struct UserInterface {
     max_x: i32,
@@ -14,17 +33,45 @@ pub struct UserInterface {
     current_mode: String,
     current_account: String,
     current_contact: String,
+    current_call: String,
+    // Call waiting to be merged with the next selected one
+    merge_call: String,
+    // Index of the selected call in the call log
+    current_record: usize,
+    current_transfer: u64,
+    current_device: String,
+    // Last PIN given to link a device to the current account
+    device_pin: String,
+    // Where to go back when leaving the calls
+    previous_mode: String,
+    // How many of the last interactions of the current contact are scrolled past
+    interactions_offset: usize,
+    // Name looked up by the name server, added as a contact once found
+    pending_contact: String,
+    status: String,
+    shutdown: Shutdown,
 }
 
 impl UserInterface {
 
-    pub fn new() -> UserInterface {
+    pub fn new(shutdown: Shutdown) -> UserInterface {
         UserInterface {
             max_x: 0,
             max_y: 0,
             current_mode: String::from("accounts"),
             current_account: String::new(),
-            current_contact: String::new()
+            current_contact: String::new(),
+            current_call: String::new(),
+            merge_call: String::new(),
+            current_record: 0,
+            current_transfer: 0,
+            current_device: String::new(),
+            device_pin: String::new(),
+            previous_mode: String::from("accounts"),
+            interactions_offset: 0,
+            pending_contact: String::new(),
+            status: String::new(),
+            shutdown,
         }
     }
 
@@ -33,6 +80,7 @@ impl UserInterface {
         setlocale(locale_conf, "");
         // Setup ncurses.
         initscr();
+        let _terminal = Terminal;
         raw();
         // Allow for extended keyboard (like F1)
         keypad(stdscr(), true);
@@ -48,24 +96,54 @@ impl UserInterface {
         self.max_y = max_y;
 
 
+        let events = manager.lock().unwrap().subscribe();
+
         let mut exit = false;
-        while !exit {
+        while !exit && !self.shutdown.is_requested() {
             refresh();
+            for event in events.try_iter() {
+                self.notify(&manager, &event);
+            }
 
             if self.current_mode == "add_account" {
-                self.draw_add_account_popup(manager.clone(), false);
+                self.draw_add_account_popup(manager.clone(), AccountSource::New);
             } else if self.current_mode == "import_account" {
-                self.draw_add_account_popup(manager.clone(), true);
+                self.draw_add_account_popup(manager.clone(), AccountSource::Archive);
+            } else if self.current_mode == "add_sip_account" {
+                self.draw_sip_account_popup(manager.clone());
+            } else if self.current_mode == "edit_account" {
+                self.draw_account_details_popup(manager.clone());
+            } else if self.current_mode == "backup_account" {
+                self.draw_backup_popup(manager.clone());
+            } else if self.current_mode == "register_name" {
+                self.draw_register_name_popup(manager.clone());
             } else if self.current_mode == "add_contact" {
                 self.draw_contacts_popup(manager.clone(), true);
             } else if self.current_mode == "send_interaction" {
                 self.draw_contacts_popup(manager.clone(), false);
+            } else if self.current_mode == "incoming_call" {
+                self.draw_incoming_call_popup(manager.clone());
+            } else if self.current_mode == "dial_pad" {
+                self.draw_dial_pad(manager.clone());
+            } else if self.current_mode == "transfer_call" {
+                self.draw_call_popup(manager.clone(), true);
+            } else if self.current_mode == "new_conference" {
+                self.draw_call_popup(manager.clone(), false);
+            } else if self.current_mode == "send_file" {
+                self.draw_path_popup(manager.clone(), true);
+            } else if self.current_mode == "accept_transfer" {
+                self.draw_path_popup(manager.clone(), false);
+            } else if self.current_mode == "revoke_device" {
+                self.draw_password_popup(manager.clone(), true);
+            } else if self.current_mode == "link_device" {
+                self.draw_password_popup(manager.clone(), false);
             } else {
                 self.draw_borders();
                 let win = newwin(self.max_y, 1, 0, self.max_x/3);
                 box_(win, 0, 0);
                 wrefresh(win);
-                if self.current_mode != "accounts" {
+                if self.current_mode != "accounts" && self.current_mode != "call" && self.current_mode != "call_log"
+                        && self.current_mode != "transfers" && self.current_mode != "devices" {
                     let win = newwin(self.max_y, 1, 0, 2*self.max_x/3);
                     box_(win, 0, 0);
                     wrefresh(win);
@@ -73,6 +151,10 @@ impl UserInterface {
                 self.draw_accounts(manager.clone());
                 self.draw_contacts(manager.clone());
                 self.draw_interactions(manager.clone());
+                self.draw_calls(manager.clone());
+                self.draw_call_log(manager.clone());
+                self.draw_transfers(manager.clone());
+                self.draw_devices(manager.clone());
                 self.draw_menu(manager.clone());
 
                 timeout(1000);
@@ -83,7 +165,8 @@ impl UserInterface {
                         let accounts = manager.lock().unwrap().accounts.clone();
                         for account in accounts {
                             if self.current_account == account.id {
-                                manager.lock().unwrap().enable_account(&*self.current_account, !account.enabled);
+                                let result = manager.lock().unwrap().enable_account(&self.current_account, !account.enabled);
+                                self.report(result);
                             }
                         }
                     } else if key == 258 /* BOTTOM KEY */ ||  key == 259 /* UP KEY */ {
@@ -109,21 +192,64 @@ impl UserInterface {
                         self.current_mode = String::from("add_account");
                     } else if key == 105 /* I */ {
                         self.current_mode = String::from("import_account");
+                    } else if key == 115 /* S */ {
+                        self.current_mode = String::from("add_sip_account");
+                    } else if key == 118 /* V */ {
+                        self.show_calls(manager.clone());
+                    } else if key == 108 /* L */ {
+                        self.show_call_log(manager.clone());
+                    } else if key == 112 /* P */ {
+                        self.previous_mode = self.current_mode.clone();
+                        self.current_mode = String::from("dial_pad");
+                    } else if key == 116 /* T */ {
+                        self.show_transfers(manager.clone());
+                    } else if key == 100 /* D */ && !self.current_account.is_empty() {
+                        self.device_pin = String::new();
+                        self.current_device = String::new();
+                        self.current_mode = String::from("devices");
+                    } else if key == 101 /* E */ {
+                        self.current_mode = String::from("edit_account");
+                    } else if key == 98 /* B */ && !self.current_account.is_empty() {
+                        self.current_mode = String::from("backup_account");
+                    } else if key == 110 /* N */ {
+                        let ring_account = manager.lock().unwrap().accounts.iter()
+                            .any(|account| account.id == self.current_account && !account.is_sip());
+                        if ring_account {
+                            self.current_mode = String::from("register_name");
+                        } else {
+                            self.status = String::from("Only Ring accounts have a registered name");
+                        }
                     } else if key == 114 /* R */ {
                         // remove account
-                        manager.lock().unwrap().rm_account(&*self.current_account);
+                        let result = manager.lock().unwrap().rm_account(&self.current_account);
+                        self.report(result);
                         self.current_account = String::new();
                     }
                 } else if self.current_mode == "contacts" {
-                    let requests = manager.lock().unwrap().get_requests(&*self.current_account);
+                    let requests = manager.lock().unwrap().get_requests(&self.current_account).unwrap_or_default();
                     if key == 27 /* ESC */ {
                         self.current_contact = String::new();
+                        self.interactions_offset = 0;
                         self.current_mode = String::from("accounts");
+                    } else if key == 339 /* PAGE UP */ {
+                        // Older interactions, loaded from the history once all the known ones are seen
+                        let page = (self.max_y - 4).max(1) as usize;
+                        let mut manager = manager.lock().unwrap();
+                        let mut known = manager.conversation(&self.current_account, &self.current_contact)
+                                               .map(|c| c.interactions.len()).unwrap_or(0);
+                        if self.interactions_offset + 2 * page > known {
+                            known += manager.load_history(&self.current_account, &self.current_contact);
+                        }
+                        self.interactions_offset = (self.interactions_offset + page).min(known.saturating_sub(1));
+                    } else if key == 338 /* PAGE DOWN */ {
+                        let page = (self.max_y - 4).max(1) as usize;
+                        self.interactions_offset = self.interactions_offset.saturating_sub(page);
                     } else if key == 258 /* BOTTOM KEY */ ||  key == 259 /* UP KEY */ {
                         // Select next account
+                        self.interactions_offset = 0;
                         let mut select = false;
-                        let mut contacts = manager.lock().unwrap().get_contacts(&*self.current_account);
-                        let mut requests = manager.lock().unwrap().get_requests(&*self.current_account);
+                        let mut contacts = manager.lock().unwrap().get_contacts_by_activity(&self.current_account).unwrap_or_default();
+                        let mut requests = manager.lock().unwrap().get_requests(&self.current_account).unwrap_or_default();
                         if key == 259 {
                             contacts.reverse();
                             requests.reverse();
@@ -148,31 +274,209 @@ impl UserInterface {
                             }
                         }
                     } else if key ==  114 /* R */ {
-                        if let Some(_) = requests.iter().position(|r| &*r == &*self.current_contact) {
-                            manager.lock().unwrap().accept_request(&*self.current_account, &*self.current_contact, false);
+                        let result = if requests.iter().position(|r| r == &*self.current_contact).is_some() {
+                            manager.lock().unwrap().accept_request(&self.current_account, &self.current_contact, false).map(|_| ())
                         } else {
-                            manager.lock().unwrap().rm_contact(&*self.current_account, &*self.current_contact, false);
-                        }
+                            manager.lock().unwrap().rm_contact(&self.current_account, &self.current_contact, false)
+                        };
+                        self.report(result);
                         self.current_contact = String::new();
                     } else if key ==  98 /* B */ {
-                        manager.lock().unwrap().rm_contact(&*self.current_account, &*self.current_contact, true);
+                        let result = manager.lock().unwrap().rm_contact(&self.current_account, &self.current_contact, true);
+                        self.report(result);
                         self.current_contact = String::new();
                     } else if key == 97 /* A */ {
-                        if let Some(_) = requests.iter().position(|r| &*r == &*self.current_contact) {
-                            manager.lock().unwrap().accept_request(&*self.current_account, &*self.current_contact, true);
+                        if requests.iter().position(|r| r == &*self.current_contact).is_some() {
+                            let result = manager.lock().unwrap().accept_request(&self.current_account, &self.current_contact, true);
+                            self.report(result);
                         } else {
                             self.current_mode = String::from("add_contact");
                         }
                     } else if key == 10 /* Enter */ {
                         self.current_mode = String::from("send_interaction");
                     } else if key == 99 /* C */ {
-                        manager.lock().unwrap().place_call(&*self.current_account, &*self.current_contact);
+                        let result = manager.lock().unwrap().place_call(&self.current_account, &self.current_contact);
+                        if let Some(call_id) = self.report(result) {
+                            self.current_call = call_id;
+                            self.previous_mode = String::from("contacts");
+                            self.current_mode = String::from("call");
+                        }
+                    } else if key == 118 /* V */ {
+                        self.show_calls(manager.clone());
+                    } else if key == 108 /* L */ {
+                        self.show_call_log(manager.clone());
+                    } else if key == 112 /* P */ {
+                        self.previous_mode = self.current_mode.clone();
+                        self.current_mode = String::from("dial_pad");
+                    } else if key == 116 /* T */ {
+                        self.show_transfers(manager.clone());
+                    } else if key == 102 /* F */ && !self.current_contact.is_empty() {
+                        self.current_mode = String::from("send_file");
+                    }
+                } else if self.current_mode == "devices" {
+                    let devices = manager.lock().unwrap().accounts.iter()
+                        .find(|account| account.id == self.current_account)
+                        .map(|account| account.devices.clone()).unwrap_or(Vec::new());
+                    if key == 27 /* ESC */ {
+                        self.current_mode = String::from("accounts");
+                    } else if key == 258 /* BOTTOM KEY */ ||  key == 259 /* UP KEY */ {
+                        // Select next device
+                        let mut select = false;
+                        let mut ids: Vec<String> = devices.iter().map(|d| d.id.clone()).collect();
+                        if key == 259 {
+                            ids.reverse();
+                        }
+                        for id in ids {
+                            if select {
+                                self.current_device = id;
+                                break;
+                            } else if self.current_device == id {
+                                select = true;
+                            }
+                        }
+                    } else if key == 114 /* R */ {
+                        match devices.iter().find(|d| d.id == self.current_device) {
+                            Some(device) if device.current => self.status = String::from("Can't revoke this device"),
+                            Some(_) => self.current_mode = String::from("revoke_device"),
+                            None => {},
+                        }
+                    } else if key == 110 /* N */ {
+                        self.current_mode = String::from("link_device");
+                    }
+                } else if self.current_mode == "transfers" {
+                    let transfers = manager.lock().unwrap().transfers.clone();
+                    let transfer = transfers.iter().find(|t| t.id == self.current_transfer).cloned();
+                    if key == 27 /* ESC */ {
+                        self.current_mode = self.previous_mode.clone();
+                    } else if key == 258 /* BOTTOM KEY */ ||  key == 259 /* UP KEY */ {
+                        // Select next transfer
+                        let mut select = false;
+                        let mut ids: Vec<u64> = transfers.iter().map(|t| t.id).collect();
+                        if key == 259 {
+                            ids.reverse();
+                        }
+                        for id in ids {
+                            if select {
+                                self.current_transfer = id;
+                                break;
+                            } else if self.current_transfer == id {
+                                select = true;
+                            }
+                        }
+                    } else if let Some(transfer) = transfer {
+                        if key == 97 /* A */ && transfer.status == TransferStatus::WaitHostAcceptance {
+                            self.current_mode = String::from("accept_transfer");
+                        } else if key == 120 /* X */ && !transfer.status.is_over() {
+                            let result = manager.lock().unwrap().cancel_transfer(transfer.id);
+                            self.report(result);
+                        }
+                    }
+                } else if self.current_mode == "call_log" {
+                    let count = manager.lock().unwrap().call_log.len();
+                    if key == 27 /* ESC */ {
+                        self.current_mode = self.previous_mode.clone();
+                    } else if key == 258 /* BOTTOM KEY */ {
+                        // Older calls are below
+                        if self.current_record > 0 {
+                            self.current_record -= 1;
+                        }
+                    } else if key == 259 /* UP KEY */ {
+                        if self.current_record + 1 < count {
+                            self.current_record += 1;
+                        }
+                    } else if key == 10 /* Enter */ || key == 99 /* C */ {
+                        let record = manager.lock().unwrap().call_log.get(self.current_record).cloned();
+                        if let Some(record) = record {
+                            let result = manager.lock().unwrap().place_call(&record.account_id, &record.peer);
+                            if let Some(call_id) = self.report(result) {
+                                self.current_call = call_id;
+                                self.current_mode = String::from("call");
+                            }
+                        }
+                    }
+                } else if self.current_mode == "call" {
+                    let call = manager.lock().unwrap().call(&self.current_call).cloned();
+                    if key == 27 /* ESC */ {
+                        self.current_mode = self.previous_mode.clone();
+                    } else if key == 258 /* BOTTOM KEY */ ||  key == 259 /* UP KEY */ {
+                        // Select next call
+                        let mut select = false;
+                        let mut calls = manager.lock().unwrap().calls.clone();
+                        if key == 259 {
+                            calls.reverse();
+                        }
+                        for call in calls {
+                            if select {
+                                self.current_call = call.id;
+                                break;
+                            } else if self.current_call == call.id {
+                                select = true;
+                            }
+                        }
+                    } else if key == 110 /* N */ {
+                        self.current_mode = String::from("new_conference");
+                    } else if let Some(call) = call {
+                        if key == 104 /* H */ {
+                            let result = manager.lock().unwrap().hang_up(&call.id);
+                            self.report(result);
+                        } else if key == ' ' as i32 {
+                            let result = if call.state == CallState::Hold {
+                                manager.lock().unwrap().unhold(&call.id)
+                            } else {
+                                manager.lock().unwrap().hold(&call.id)
+                            };
+                            self.report(result);
+                        } else if key == 109 /* M */ {
+                            let result = manager.lock().unwrap().mute(&call.id, !call.muted);
+                            self.report(result);
+                        } else if key == 97 /* A */ && call.state == CallState::Incoming {
+                            let result = manager.lock().unwrap().accept_call(&call.id);
+                            self.report(result);
+                        } else if key == 114 /* R */ && call.state == CallState::Incoming {
+                            let result = manager.lock().unwrap().refuse_call(&call.id);
+                            self.report(result);
+                        } else if key == 112 /* P */ {
+                            self.current_mode = String::from("dial_pad");
+                        } else if key == 116 /* T */ {
+                            self.current_mode = String::from("transfer_call");
+                        } else if key == 106 /* J */ {
+                            if self.merge_call.is_empty() || self.merge_call == call.id {
+                                self.merge_call = call.id.clone();
+                                self.status = String::from("Select the call to merge with and press J");
+                            } else {
+                                let merge_call = self.merge_call.clone();
+                                self.merge_call = String::new();
+                                let result = manager.lock().unwrap().join_calls(&call.id, &merge_call);
+                                self.report(result);
+                            }
+                        } else if key == 100 /* D */ {
+                            let result = manager.lock().unwrap().detach_call(&call.id);
+                            self.report(result);
+                        } else if key >= '1' as i32 && key <= '9' as i32 {
+                            // Conferences are numbered from 1 in the calls view
+                            let index = (key - '1' as i32) as usize;
+                            let conference = manager.lock().unwrap().conferences.get(index).map(|c| c.id.clone());
+                            match conference {
+                                Some(conference_id) => {
+                                    let result = manager.lock().unwrap().add_to_conference(&call.id, &conference_id);
+                                    self.report(result);
+                                },
+                                None => self.status = format!("No conference {}", index + 1),
+                            }
+                        } else if key == 120 /* X */ {
+                            let conference = manager.lock().unwrap().conference_of(&call.id).map(|c| c.id.clone());
+                            match conference {
+                                Some(conference_id) => {
+                                    let result = manager.lock().unwrap().hang_up_conference(&conference_id);
+                                    self.report(result);
+                                },
+                                None => self.status = String::from("Not in a conference"),
+                            }
+                        }
                     }
                 }
             }
         }
-
-        endwin();
     }
 
     fn init_colors(&mut self) {
@@ -180,6 +484,7 @@ impl UserInterface {
         init_color(COLOR_BLACK, 0, 0, 0);
         init_color(COLOR_WHITE, 255 * 4, 255 * 4, 255 * 4);
         init_pair(COLOR_SELECTED, COLOR_BLACK, COLOR_WHITE);
+        init_pair(COLOR_FAILED, COLOR_RED, COLOR_BLACK);
     }
 
     fn draw_borders(&mut self) -> WINDOW {
@@ -187,27 +492,145 @@ impl UserInterface {
         box_(win, 0, 0);
         wrefresh(win);
         mvprintw(LINES() - 2, 1, "ruring v1.0.0");
+        if !self.status.is_empty() {
+            mvprintw(LINES() - 2, 16, &self.status);
+        }
         win
     }
 
+    /**
+     * Show new events in the status bar
+     * @param self
+     * @param event from the manager
+     */
+    fn notify(&mut self, manager: &Arc<Mutex<Manager>>, event: &RingEvent) {
+        match *event {
+            RingEvent::IncomingMessage { ref interaction, .. } => {
+                self.status = format!("New message from {}", interaction.author_ring_id);
+            },
+            RingEvent::IncomingTrustRequest { ref account_id, ref from } => {
+                self.status = format!("New request from {} for {}", from, account_alias(manager, account_id));
+            },
+            RingEvent::IncomingCall { ref call_id, ref from, .. } => {
+                self.status = format!("Incoming call from {}", from);
+                // Popups are not interrupted, the call stays in the calls view
+                if self.current_mode == "accounts" || self.current_mode == "contacts" {
+                    self.previous_mode = self.current_mode.clone();
+                }
+                if self.current_mode == "accounts" || self.current_mode == "contacts" || self.current_mode == "call" {
+                    self.current_call = call_id.clone();
+                    self.current_mode = String::from("incoming_call");
+                }
+            },
+            RingEvent::CallStateChanged { ref state, .. } if state.is_over() => {
+                self.status = format!("Call {}", state);
+            },
+            RingEvent::RegisteredNameFound { ref account_id, status, ref address, ref name }
+                    if !self.pending_contact.is_empty() && *name == self.pending_contact => {
+                self.pending_contact = String::new();
+                match status {
+                    LookupStatus::Found => {
+                        let result = manager.lock().unwrap().add_contact(account_id, address);
+                        if self.report(result).is_some() {
+                            self.status = format!("{} added", name);
+                        }
+                    },
+                    LookupStatus::InvalidName | LookupStatus::NotFound => {
+                        self.report::<()>(Err(RingError::NameNotFound(name.clone())));
+                    },
+                    LookupStatus::Error => self.status = format!("Can't look {} up", name),
+                }
+            },
+            RingEvent::NameRegistrationEnded { state, ref name, .. } => {
+                if state == NameRegistrationState::Success {
+                    self.status = format!("{} registered", name);
+                } else {
+                    self.status = format!("Can't register {}: {}", name, state);
+                }
+            },
+            RingEvent::AccountImportEnded { ref account_id, ref registration } => {
+                let alias = account_alias(manager, account_id);
+                if registration.is_error() {
+                    self.status = format!("Can't import {}: {}", alias, registration);
+                } else {
+                    self.status = format!("{} imported", alias);
+                }
+            },
+            RingEvent::DeviceRevocationEnded { ref device_id, status, .. } => {
+                self.status = format!("Device {}: {}", device_id, status);
+            },
+            RingEvent::ExportOnRingEnded { ref account_id, status, ref pin } => {
+                if status == ExportOnRingStatus::Success && *account_id == self.current_account {
+                    self.device_pin = pin.clone();
+                    self.status = format!("Enter the PIN {} on the new device", pin);
+                } else {
+                    self.status = format!("Can't link a device: {}", status);
+                }
+            },
+            RingEvent::DataTransferEvent { transfer_id, status } => {
+                if status == TransferStatus::WaitHostAcceptance {
+                    self.current_transfer = transfer_id;
+                    self.status = String::from("Incoming file, press T to see it");
+                } else if status.is_over() {
+                    self.status = format!("File transfer {}", status);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /**
+     * Show in the status bar why the last daemon call failed
+     * @param self
+     * @param result of the daemon call
+     * @return the value if the call succeed
+     */
+    fn report<T>(&mut self, result: Result<T, RingError>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.status = String::new();
+                Some(value)
+            },
+            Err(e) => {
+                error!("{}", e);
+                self.status = format!("Error: {}", e);
+                None
+            }
+        }
+    }
+
     fn draw_menu(&mut self, manager: Arc<Mutex<Manager>>) {
         let attr = COLOR_PAIR(COLOR_SELECTED);
         let mut menu_str = String::new();
         if self.current_mode == "accounts" {
-            menu_str = String::from("ESC: quit | A: Add | R: Remove | SPACE: Enable | I: Import | Enter: Select");
+            menu_str = String::from("ESC: quit | A: Add | S: Add SIP | R: Remove | SPACE: Enable | I: Import | E: Edit | B: Backup | D: Devices | N: Register name | Enter: Select | V: Calls | L: Call log | P: Dial pad | T: Transfers");
         } else if self.current_mode == "contacts" {
-            let requests = manager.lock().unwrap().get_requests(&*self.current_account);
-            if let Some(_) = requests.iter().position(|r| &*r == &*self.current_contact) {
+            let requests = manager.lock().unwrap().get_requests(&self.current_account).unwrap_or_default();
+            if requests.iter().position(|r| r == &*self.current_contact).is_some() {
                 menu_str = String::from("ESC: return | A: Accept | R: Discard");
             } else {
-                menu_str = String::from("ESC: return | A: Add | R: Remove | W: Send message");
+                menu_str = String::from("ESC: return | A: Add | R: Remove | Enter: Send message | PgUp/PgDn: Older/newer messages | C: Call | F: Send file | V: Calls | L: Call log | P: Dial pad | T: Transfers");
+            }
+        } else if self.current_mode == "call_log" {
+            menu_str = String::from("ESC: return | Enter: Call back");
+        } else if self.current_mode == "devices" {
+            menu_str = String::from("ESC: return | N: Link a new device | R: Revoke");
+        } else if self.current_mode == "transfers" {
+            menu_str = String::from("ESC: return | A: Accept | X: Cancel");
+        } else if self.current_mode == "call" {
+            let incoming = manager.lock().unwrap().call(&self.current_call)
+                                  .map(|call| call.state == CallState::Incoming).unwrap_or(false);
+            if incoming {
+                menu_str = String::from("ESC: return | A: Accept | R: Refuse");
+            } else {
+                menu_str = String::from("ESC: return | H: Hang up | SPACE: Hold | M: Mute | P: Dial pad | T: Transfer | J: Merge | 1-9: Add to conference | N: New conference | D: Detach | X: End conference");
             }
         }
         while menu_str.len() < self.max_x as usize {
             menu_str += " ";
         }
         attron(attr);
-        mvprintw(0, 0, &*menu_str);
+        mvprintw(0, 0, &menu_str);
         attroff(attr);
     }
 
@@ -224,25 +647,35 @@ impl UserInterface {
             } else {
                 account_str += "[ ] ";
             }
-            let account_identity = format!("{} ({})", account.alias, account.ring_id);
+            let account_identity = if account.is_sip() {
+                format!("{} (SIP {}@{}) - {}", account.alias, account.details.username,
+                        account.details.hostname, account.registration)
+            } else if !account.registered_name.is_empty() {
+                format!("{} ({} {}) - {}", account.alias, account.registered_name, account.ring_id, account.registration)
+            } else {
+                format!("{} ({}) - {}", account.alias, account.ring_id, account.registration)
+            };
             account_str += &*account_identity;
             let mut set_focus = false;
             if self.current_mode == "accounts" {
-                if self.current_account.len() == 0 {
+                if self.current_account.is_empty() {
                     self.current_account = account.id;
                     set_focus = true;
                 } else if self.current_account == account.id {
                     set_focus = true;
                 }
             }
-            let attr = COLOR_PAIR(COLOR_SELECTED);
-            if set_focus {
-                attron(attr);
-            }
-            mvprintw(row, 2, &*account_str);
-            if set_focus {
-                attroff(attr);
-            }
+            // Failed registrations stand out, unless selected
+            let attr = if set_focus {
+                COLOR_PAIR(COLOR_SELECTED)
+            } else if account.registration.is_error() {
+                COLOR_PAIR(COLOR_FAILED)
+            } else {
+                A_NORMAL()
+            };
+            attron(attr);
+            mvprintw(row, 2, &account_str);
+            attroff(attr);
             row += 1;
         }
     }
@@ -251,8 +684,14 @@ impl UserInterface {
         if self.current_mode == "contacts" {
             let mut row = 3;
             // Current requests
-            let requests = manager.lock().unwrap().get_requests(&*self.current_account);
-            if requests.len() != 0 {
+            let requests = match manager.lock().unwrap().get_requests(&self.current_account) {
+                Ok(requests) => requests,
+                Err(e) => {
+                    self.status = format!("Error: {}", e);
+                    Vec::new()
+                }
+            };
+            if !requests.is_empty() {
                 attron(A_BOLD());
                 mvprintw(row, self.max_x/3 + 4, "Requests:");
                 attroff(A_BOLD());
@@ -260,7 +699,7 @@ impl UserInterface {
                 for contact in requests {
                     let mut set_focus = false;
                     if self.current_mode == "contacts" {
-                        if self.current_contact.len() == 0 {
+                        if self.current_contact.is_empty() {
                             self.current_contact = contact.clone();
                             set_focus = true;
                         } else if self.current_contact == contact {
@@ -271,7 +710,8 @@ impl UserInterface {
                     if set_focus {
                         attron(attr);
                     }
-                    mvprintw(row, self.max_x/3 + 4, &*contact);
+                    let contact_str = self.contact_label(&manager, &self.current_account, &contact);
+                    mvprintw(row, self.max_x/3 + 4, &contact_str);
                     if set_focus {
                         attroff(attr);
                     }
@@ -284,21 +724,40 @@ impl UserInterface {
             mvprintw(row, self.max_x/3 + 4, "Contacts:");
             attroff(A_BOLD());
             row += 2;
-            for contact in manager.lock().unwrap().get_contacts(&*self.current_account) {
+            let contacts = match manager.lock().unwrap().get_contacts_by_activity(&self.current_account) {
+                Ok(contacts) => contacts,
+                Err(e) => {
+                    self.status = format!("Error: {}", e);
+                    Vec::new()
+                }
+            };
+            for contact in contacts {
                 let mut set_focus = false;
                 if self.current_mode == "contacts" {
-                    if self.current_contact.len() == 0 {
+                    if self.current_contact.is_empty() {
                         self.current_contact = contact.clone();
                         set_focus = true;
                     } else if self.current_contact == contact {
                         set_focus = true;
                     }
                 }
+                let unread = manager.lock().unwrap().conversation(&self.current_account, &contact)
+                                    .map(|c| c.unread()).unwrap_or(0);
+                let mut contact_str = self.contact_label(&manager, &self.current_account, &contact);
+                if unread != 0 {
+                    contact_str += &*format!(" ({})", unread);
+                }
                 let attr = COLOR_PAIR(COLOR_SELECTED);
                 if set_focus {
                     attron(attr);
                 }
-                mvprintw(row, self.max_x/3 + 4, &*contact);
+                if unread != 0 {
+                    attron(A_BOLD());
+                }
+                mvprintw(row, self.max_x/3 + 4, &contact_str);
+                if unread != 0 {
+                    attroff(A_BOLD());
+                }
                 if set_focus {
                     attroff(attr);
                 }
@@ -307,7 +766,373 @@ impl UserInterface {
         }
     }
 
-    fn draw_add_account_popup(&mut self, manager: Arc<Mutex<Manager>>, import: bool) {
+    /**
+     * @param self
+     * @param manager
+     * @param account_id account knowing the contact
+     * @param contact ring id
+     * @return the registered name of the contact next to its ring id, if known
+     */
+    fn contact_label(&self, manager: &Arc<Mutex<Manager>>, account_id: &str, contact: &str) -> String {
+        match manager.lock().unwrap().registered_name(account_id, contact) {
+            Some(name) => format!("{} ({})", name, contact),
+            None => contact.to_string(),
+        }
+    }
+
+    /**
+     * Go to the calls view, if there are calls
+     * @param self
+     * @param manager
+     */
+    fn show_calls(&mut self, manager: Arc<Mutex<Manager>>) {
+        if manager.lock().unwrap().calls.is_empty() {
+            self.status = String::from("No call");
+            return;
+        }
+        self.previous_mode = self.current_mode.clone();
+        self.current_mode = String::from("call");
+    }
+
+    /**
+     * Show the devices of the current account, and the PIN to link a new one
+     * @param self
+     * @param manager
+     */
+    fn draw_devices(&mut self, manager: Arc<Mutex<Manager>>) {
+        if self.current_mode != "devices" {
+            return;
+        }
+        let account = manager.lock().unwrap().accounts.iter()
+            .find(|account| account.id == self.current_account).cloned();
+        let account = match account {
+            Some(account) => account,
+            None => {
+                self.current_mode = String::from("accounts");
+                return;
+            }
+        };
+        if !account.devices.iter().any(|d| d.id == self.current_device) {
+            self.current_device = account.devices.first().map(|d| d.id.clone()).unwrap_or(String::new());
+        }
+        let mut row = 3;
+        attron(A_BOLD());
+        mvprintw(row, self.max_x/3 + 4, &format!("Devices of {}:", account.alias));
+        attroff(A_BOLD());
+        row += 2;
+        for device in account.devices.iter() {
+            let mut device_str = format!("{} ({})", device.name, device.id);
+            if device.current {
+                device_str += " [THIS DEVICE]";
+            }
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            let set_focus = device.id == self.current_device;
+            if set_focus {
+                attron(attr);
+            }
+            mvprintw(row, self.max_x/3 + 4, &device_str);
+            if set_focus {
+                attroff(attr);
+            }
+            row += 1;
+        }
+        if !self.device_pin.is_empty() {
+            row += 1;
+            attron(A_BOLD());
+            mvprintw(row, self.max_x/3 + 4, &format!("PIN to link a new device: {}", self.device_pin));
+            attroff(A_BOLD());
+        }
+    }
+
+    /**
+     * Go to the file transfers view, if there are transfers
+     * @param self
+     * @param manager
+     */
+    fn show_transfers(&mut self, manager: Arc<Mutex<Manager>>) {
+        let last = manager.lock().unwrap().transfers.last().map(|t| t.id);
+        let last = match last {
+            Some(last) => last,
+            None => {
+                self.status = String::from("No file transfer");
+                return;
+            }
+        };
+        if manager.lock().unwrap().data_transfer(self.current_transfer).is_none() {
+            self.current_transfer = last;
+        }
+        self.previous_mode = self.current_mode.clone();
+        self.current_mode = String::from("transfers");
+    }
+
+    /**
+     * Show the file transfers with their progress
+     * @param self
+     * @param manager
+     */
+    fn draw_transfers(&mut self, manager: Arc<Mutex<Manager>>) {
+        if self.current_mode != "transfers" {
+            return;
+        }
+        let transfers = {
+            let mut manager = manager.lock().unwrap();
+            manager.refresh_progress();
+            manager.transfers.clone()
+        };
+        let mut row = 3;
+        attron(A_BOLD());
+        mvprintw(row, self.max_x/3 + 4, "File transfers:");
+        attroff(A_BOLD());
+        row += 2;
+        for transfer in transfers.iter() {
+            let arrow = if transfer.direction == Direction::Incoming {"<-"} else {"->"};
+            let bar_size = 20;
+            let done = (transfer.ratio() * bar_size as f64) as usize;
+            let bar = format!("[{}{}] {:3}%", "#".repeat(done), "-".repeat(bar_size - done), (transfer.ratio() * 100.) as u32);
+            let transfer_str = format!("{} {} {} {} - {}", arrow, transfer.display_name,
+                                       self.contact_label(&manager, &transfer.account_id, &transfer.peer), bar, transfer.status);
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            let set_focus = transfer.id == self.current_transfer;
+            if set_focus {
+                attron(attr);
+            }
+            mvprintw(row, self.max_x/3 + 4, &transfer_str);
+            if set_focus {
+                attroff(attr);
+            }
+            row += 1;
+        }
+    }
+
+    /**
+     * Go to the call log, if there are finished calls
+     * @param self
+     * @param manager
+     */
+    fn show_call_log(&mut self, manager: Arc<Mutex<Manager>>) {
+        let count = manager.lock().unwrap().call_log.len();
+        if count == 0 {
+            self.status = String::from("No call yet");
+            return;
+        }
+        self.current_record = count - 1;
+        self.previous_mode = self.current_mode.clone();
+        self.current_mode = String::from("call_log");
+    }
+
+    /**
+     * Show the finished calls, most recent first, with their direction, duration and outcome
+     * @param self
+     * @param manager
+     */
+    fn draw_call_log(&mut self, manager: Arc<Mutex<Manager>>) {
+        if self.current_mode != "call_log" {
+            return;
+        }
+        let call_log = manager.lock().unwrap().call_log.clone();
+        let mut row = 3;
+        attron(A_BOLD());
+        mvprintw(row, self.max_x/3 + 4, "Call log:");
+        attroff(A_BOLD());
+        row += 2;
+        for (index, record) in call_log.iter().enumerate().rev() {
+            if row >= self.max_y - 3 {
+                break;
+            }
+            let arrow = if record.direction == Direction::Incoming {"<-"} else {"->"};
+            let mut record_str = format!("{} {} {} - {}", record.start.rfc3339(), arrow,
+                                         self.contact_label(&manager, &record.account_id, &record.peer), record.outcome);
+            if record.outcome == CallOutcome::Answered {
+                let duration = record.duration;
+                record_str += &*format!(" {:02}:{:02}", duration.num_minutes(), duration.num_seconds() % 60);
+            }
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            let set_focus = index == self.current_record;
+            if set_focus {
+                attron(attr);
+            }
+            mvprintw(row, self.max_x/3 + 4, &record_str);
+            if set_focus {
+                attroff(attr);
+            }
+            row += 1;
+        }
+    }
+
+    /**
+     * Show the current calls with their state, duration, hold and mute indicators
+     * @param self
+     * @param manager
+     */
+    fn draw_calls(&mut self, manager: Arc<Mutex<Manager>>) {
+        if self.current_mode != "call" {
+            return;
+        }
+        let calls = manager.lock().unwrap().calls.clone();
+        if calls.is_empty() {
+            self.current_mode = self.previous_mode.clone();
+            return;
+        }
+        if !calls.iter().any(|call| call.id == self.current_call) {
+            self.current_call = calls[0].id.clone();
+        }
+        if !calls.iter().any(|call| call.id == self.merge_call) {
+            self.merge_call = String::new();
+        }
+        let conferences = manager.lock().unwrap().conferences.clone();
+        let mut row = 3;
+        attron(A_BOLD());
+        mvprintw(row, self.max_x/3 + 4, "Calls:");
+        attroff(A_BOLD());
+        row += 2;
+        for call in calls.iter() {
+            let mut call_str = format!("{} - {}", self.contact_label(&manager, &call.account_id, &call.peer), call.state);
+            if let Some(duration) = call.duration() {
+                call_str += &*format!(" {:02}:{:02}", duration.num_minutes(), duration.num_seconds() % 60);
+            }
+            if call.state == CallState::Hold {
+                call_str += " [HOLD]";
+            }
+            if call.muted {
+                call_str += " [MUTED]";
+            }
+            if let Some(index) = conferences.iter().position(|c| c.participants.contains(&call.id)) {
+                call_str += &*format!(" [CONF {}]", index + 1);
+            }
+            if call.id == self.merge_call {
+                call_str += " [MERGE]";
+            }
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            let set_focus = call.id == self.current_call;
+            if set_focus {
+                attron(attr);
+            }
+            mvprintw(row, self.max_x/3 + 4, &call_str);
+            if set_focus {
+                attroff(attr);
+            }
+            row += 1;
+        }
+        if conferences.is_empty() {
+            return;
+        }
+        row += 1;
+        attron(A_BOLD());
+        mvprintw(row, self.max_x/3 + 4, "Conferences:");
+        attroff(A_BOLD());
+        row += 2;
+        for (index, conference) in conferences.iter().enumerate() {
+            let participants: Vec<String> = conference.participants.iter()
+                .filter_map(|participant| calls.iter().find(|call| call.id == *participant))
+                .map(|call| self.contact_label(&manager, &call.account_id, &call.peer))
+                .collect();
+            let conference_str = format!("{}. {} - {}", index + 1, participants.join(", "), conference.state);
+            mvprintw(row, self.max_x/3 + 4, &conference_str);
+            row += 1;
+        }
+    }
+
+    /**
+     * Ask to accept or refuse the incoming call
+     * @param self
+     * @param manager
+     */
+    fn draw_incoming_call_popup(&mut self, manager: Arc<Mutex<Manager>>) {
+        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 5);
+        let mut exit = false;
+        let mut focus = "accept_btn";
+
+        timeout(1000);
+        while !exit && !self.shutdown.is_requested() {
+            let call = manager.lock().unwrap().call(&self.current_call).cloned();
+            let call = match call {
+                Some(call) => call,
+                None => {
+                    // The caller gave up
+                    self.leave_incoming_call(manager.clone());
+                    return;
+                }
+            };
+            if call.state != CallState::Incoming {
+                // Answered from another client
+                self.current_mode = String::from("call");
+                return;
+            }
+
+            let win = newwin(10, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+            wrefresh(win);
+
+            let title = "Incoming call";
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, title);
+            let from = format!("From: {}", self.contact_label(&manager, &call.account_id, &call.peer));
+            mvprintw(start_y + 4, start_x + 2, &from);
+
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            if focus == "accept_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 7, self.max_x/2 - 6 - "< Accept >".len() as i32, "< Accept >");
+            if focus == "accept_btn" {
+                attroff(attr);
+            }
+            if focus == "refuse_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 7, self.max_x/2 + 6, "< Refuse >");
+            if focus == "refuse_btn" {
+                attroff(attr);
+            }
+
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                // The call keeps ringing, V shows it again
+                self.leave_incoming_call(manager.clone());
+                exit = true;
+            } else if key == 9 /* TAB */ || key == 260 /* LEFT */ || key == 261 /* RIGHT */ {
+                focus = if focus == "accept_btn" {"refuse_btn"} else {"accept_btn"};
+            } else if key == 10 /* ENTER */ {
+                if focus == "accept_btn" {
+                    let result = manager.lock().unwrap().accept_call(&call.id);
+                    if self.report(result).is_some() {
+                        self.current_mode = String::from("call");
+                    } else {
+                        self.leave_incoming_call(manager.clone());
+                    }
+                } else {
+                    let result = manager.lock().unwrap().refuse_call(&call.id);
+                    self.report(result);
+                    self.leave_incoming_call(manager.clone());
+                }
+                exit = true;
+            }
+        }
+    }
+
+    /**
+     * Go back to the calls if some are left, else to where we were
+     * @param self
+     * @param manager
+     */
+    fn leave_incoming_call(&mut self, manager: Arc<Mutex<Manager>>) {
+        let other_calls = manager.lock().unwrap().calls.iter().any(|call| call.id != self.current_call);
+        if other_calls {
+            self.current_mode = String::from("call");
+        } else {
+            self.current_mode = self.previous_mode.clone();
+        }
+    }
+
+    /**
+     * Create or import a RING account. TAB to the mode to switch between
+     * a new account, an archive and a PIN given by another device.
+     * @param self
+     * @param manager
+     * @param source mode selected when opening the popup
+     */
+    fn draw_add_account_popup(&mut self, manager: Arc<Mutex<Manager>>, source: AccountSource) {
+        let mut source = source;
         let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 8);
 
         let mut username = String::new();
@@ -315,28 +1140,47 @@ impl UserInterface {
         let mut exit = false;
         let mut focus = "username";
 
-        while !exit {
+        while !exit && !self.shutdown.is_requested() {
             let win = newwin(16, self.max_x/2, start_y, start_x);
             box_(win, 0, 0);
 
             let title = "Add new RING account";
             mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, title);
 
-            let first_info = if import {"Path:"} else {"Username:"};
+            let first_info = match source {
+                AccountSource::New => "Username:",
+                AccountSource::Archive => "Path:",
+                AccountSource::Pin => "PIN:",
+            };
             let second_info = "Password:";
             let start_label = start_x + 2;
             let label_size = std::cmp::max(first_info.len(), second_info.len()) as i32;
             let start_edit_view = start_x + label_size + 6;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
 
-            mvprintw(start_y + 4, start_label,first_info);
+            mvprintw(start_y + 4, start_label, "Mode:");
+            let mut mode_x = start_edit_view;
+            for mode in [AccountSource::New, AccountSource::Archive, AccountSource::Pin].iter() {
+                let mode_str = format!("< {} >", mode);
+                if *mode == source {
+                    attron(if focus == "source" {attr} else {A_BOLD()});
+                }
+                mvprintw(start_y + 4, mode_x, &mode_str);
+                if *mode == source {
+                    attroff(if focus == "source" {attr} else {A_BOLD()});
+                }
+                mode_x += mode_str.len() as i32 + 1;
+            }
+
+            // Cleared, as the label changes with the mode
+            mvprintw(start_y + 6, start_label, &format!("{:1$}", first_info, label_size as usize));
             let width = self.max_x/2 - label_size - 12;
-            let attr = COLOR_PAIR(COLOR_SELECTED);
             attron(attr);
             let mut username_entry = username.clone();
             for _ in 0..(width - username.len() as i32) {
                 username_entry += " ";
             }
-            mvprintw(start_y + 4, start_edit_view, &*username_entry);
+            mvprintw(start_y + 6, start_edit_view, &username_entry);
             attroff(attr);
 
 
@@ -349,7 +1193,7 @@ impl UserInterface {
             for _ in 0..(width - password.len() as i32) {
                 password_entry += " ";
             }
-            mvprintw(start_y + 8, start_edit_view, &*password_entry);
+            mvprintw(start_y + 8, start_edit_view, &password_entry);
             attroff(attr);
             wrefresh(win);
 
@@ -378,16 +1222,22 @@ impl UserInterface {
                     "username" => "password",
                     "password" => "ok_btn",
                     "ok_btn" => "cancel_btn",
-                    "cancel_btn" => "username",
+                    "cancel_btn" => "source",
+                    "source" => "username",
                     _ => {
                         exit = true;
                         ""
                     }
                 }
+            } else if focus == "source" && (key == 10 /* ENTER */ || key == 32 /* SPACE */) {
+                source = source.next();
             } else if key == 10 /* ENTER */ {
                 match focus {
                     "ok_btn" => {
-                        manager.lock().unwrap().add_account(&*username, &*password, import);
+                        let result = manager.lock().unwrap().add_account(&username, &password, source);
+                        if self.report(result).is_some() && source == AccountSource::Pin {
+                            self.status = String::from("Importing the account from the other device...");
+                        }
                         self.current_mode = String::from("accounts");
                         exit = true;
                     },
@@ -417,6 +1267,7 @@ impl UserInterface {
                     },
                     "ok_btn" => {},
                     "cancel_btn" => {},
+                    "source" => {},
                     _ => {
                         exit = true;
                     }
@@ -425,26 +1276,525 @@ impl UserInterface {
         }
     }
 
-    fn draw_contacts_popup(&mut self, manager: Arc<Mutex<Manager>>, add: bool) {
-        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 5);
+    /**
+     * Create a SIP account. SPACE changes the transport and toggles the options,
+     * the TLS ones are only shown with the TLS transport.
+     * @param self
+     * @param manager
+     */
+    fn draw_sip_account_popup(&mut self, manager: Arc<Mutex<Manager>>) {
+        let (start_x, start_y) = (self.max_x/4, std::cmp::max(0, self.max_y/2 - 10));
 
-        let mut entry = String::new();
+        let mut settings = SipSettings::new("", "", "");
+        let mut port = String::new();
         let mut exit = false;
-        let mut focus = "entry";
+        let mut focus = "hostname";
 
-        while !exit {
-            let win = newwin(10, self.max_x/2, start_y, start_x);
+        while !exit && !self.shutdown.is_requested() {
+            let tls = settings.transport == SipTransport::Tls;
+            let mut fields = vec![("alias", "Alias:"), ("hostname", "Hostname:"), ("username", "Username:"),
+                                  ("password", "Password:"), ("transport", "Transport:")];
+            if tls {
+                fields.extend_from_slice(&[("tls_port", "TLS port:"), ("ca_list", "CA list:"),
+                                           ("certificate", "Certificate:"), ("private_key", "Private key:"),
+                                           ("verify_server", "Verify server:")]);
+            }
+            fields.push(("srtp", "SRTP:"));
+
+            let win = newwin(20, self.max_x/2, start_y, start_x);
             box_(win, 0, 0);
+            wrefresh(win);
 
-            let title = if add {"Add new contact"} else {"Send message"};
+            let title = "Add new SIP account";
             mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, title);
 
-            let first_info = if add {"Id:"} else {"Message:"};
             let start_label = start_x + 2;
-            let label_size = first_info.len() as i32;
+            let label_size = fields.iter().map(|f| f.1.len()).max().unwrap_or(0) as i32;
             let start_edit_view = start_x + label_size + 6;
-
-            mvprintw(start_y + 4, start_label,first_info);
+            let width = self.max_x/2 - label_size - 12;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+
+            let mut row = start_y + 4;
+            for &(field, label) in fields.iter() {
+                mvprintw(row, start_label, label);
+                let check = |checked: bool| String::from(if checked {"[x]"} else {"[ ]"});
+                let (mut value, is_entry) = match field {
+                    "alias" => (settings.alias.clone(), true),
+                    "hostname" => (settings.hostname.clone(), true),
+                    "username" => (settings.username.clone(), true),
+                    "password" => (settings.password.chars().map(|_| '*').collect(), true),
+                    "transport" => (format!("< {} >", settings.transport), false),
+                    "tls_port" => (port.clone(), true),
+                    "ca_list" => (settings.tls_ca_list_file.clone(), true),
+                    "certificate" => (settings.tls_certificate_file.clone(), true),
+                    "private_key" => (settings.tls_private_key_file.clone(), true),
+                    "verify_server" => (check(settings.tls_verify_server), false),
+                    _ => (check(settings.srtp), false),
+                };
+                if is_entry {
+                    while (value.len() as i32) < width {
+                        value += " ";
+                    }
+                }
+                if is_entry || focus == field {
+                    attron(attr);
+                }
+                mvprintw(row, start_edit_view, &value);
+                if is_entry || focus == field {
+                    attroff(attr);
+                }
+                row += 1;
+            }
+            // Clear the rows of the TLS options when they are hidden
+            while row < start_y + 15 {
+                mvprintw(row, start_label, &format!("{:1$}", "", (self.max_x/2 - 4) as usize));
+                row += 1;
+            }
+
+            if focus == "ok_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 17, self.max_x/2 - 6 - "< OK >".len() as i32, "< OK >");
+            if focus == "ok_btn" {
+                attroff(attr);
+            }
+            if focus == "cancel_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 17, self.max_x/2 + 6, "< Cancel >");
+            if focus == "cancel_btn" {
+                attroff(attr);
+            }
+
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                exit = true;
+            } else if key == 9 /* TAB */ {
+                let mut order: Vec<&str> = fields.iter().map(|f| f.0).collect();
+                order.push("ok_btn");
+                order.push("cancel_btn");
+                let idx = order.iter().position(|f| *f == focus).unwrap_or(0);
+                focus = order[(idx + 1) % order.len()];
+            } else if key == 10 /* ENTER */ {
+                match focus {
+                    "ok_btn" => {
+                        settings.tls_listener_port = port.parse().unwrap_or(0);
+                        let result = manager.lock().unwrap().add_sip_account(&settings);
+                        if let Some(account) = self.report(result) {
+                            self.current_account = account.id;
+                        }
+                        exit = true;
+                    },
+                    "cancel_btn" => {
+                        exit = true;
+                    },
+                    _ => { }
+                }
+            } else if key == 32 /* SPACE */ && (focus == "transport" || focus == "verify_server" || focus == "srtp") {
+                match focus {
+                    "transport" => settings.transport = settings.transport.next(),
+                    "verify_server" => settings.tls_verify_server = !settings.tls_verify_server,
+                    _ => settings.srtp = !settings.srtp,
+                }
+            } else if key == 263 /* BACKSPACE */ {
+                match focus {
+                    "alias" => { settings.alias.pop(); },
+                    "hostname" => { settings.hostname.pop(); },
+                    "username" => { settings.username.pop(); },
+                    "password" => { settings.password.pop(); },
+                    "tls_port" => { port.pop(); },
+                    "ca_list" => { settings.tls_ca_list_file.pop(); },
+                    "certificate" => { settings.tls_certificate_file.pop(); },
+                    "private_key" => { settings.tls_private_key_file.pop(); },
+                    _ => { }
+                }
+            } else {
+                let c = std::char::from_u32(key as u32).unwrap_or(' ');
+                match focus {
+                    "alias" => settings.alias.push(c),
+                    "hostname" => settings.hostname.push(c),
+                    "username" => settings.username.push(c),
+                    "password" => settings.password.push(c),
+                    "tls_port"
+                        if c.is_ascii_digit() && format!("{}{}", port, c).parse::<u16>().is_ok() => {
+                            port.push(c);
+                        },
+                    "ca_list" => settings.tls_ca_list_file.push(c),
+                    "certificate" => settings.tls_certificate_file.push(c),
+                    "private_key" => settings.tls_private_key_file.push(c),
+                    _ => { }
+                }
+            }
+        }
+        self.current_mode = String::from("accounts");
+    }
+
+    /**
+     * Export the archive of the current account to a file, and/or change its password.
+     * The archive is exported with the new password if one is given.
+     * @param self
+     * @param manager
+     */
+    fn draw_backup_popup(&mut self, manager: Arc<Mutex<Manager>>) {
+        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 8);
+
+        let alias = manager.lock().unwrap().accounts.iter()
+            .find(|account| account.id == self.current_account)
+            .map(|account| account.alias.clone()).unwrap_or(String::new());
+        let home = std::env::var("HOME").unwrap_or(String::from("."));
+        let mut path = format!("{}/{}.gz", home, alias);
+        let mut password = String::new();
+        let mut new_password = String::new();
+        let mut exit = false;
+        let mut focus = "path";
+
+        while !exit && !self.shutdown.is_requested() {
+            let win = newwin(16, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+
+            let title = format!("Backup {}", alias);
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, &title);
+
+            let labels = ["Archive path:", "Password:", "New password:"];
+            let start_label = start_x + 2;
+            let label_size = labels.iter().map(|l| l.len()).max().unwrap_or(0) as i32;
+            let start_edit_view = start_x + label_size + 6;
+            let width = self.max_x/2 - label_size - 12;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+
+            let masked_password: String = password.chars().map(|_| '*').collect();
+            let masked_new_password: String = new_password.chars().map(|_| '*').collect();
+            let entries = [&path, &masked_password, &masked_new_password];
+            for (idx, label) in labels.iter().enumerate() {
+                let row = start_y + 4 + 2 * idx as i32;
+                mvprintw(row, start_label, label);
+                attron(attr);
+                let mut info_entry = entries[idx].clone();
+                for _ in 0..(width - entries[idx].len() as i32) {
+                    info_entry += " ";
+                }
+                mvprintw(row, start_edit_view, &info_entry);
+                attroff(attr);
+            }
+            mvprintw(start_y + 10, start_label, "Leave the path empty to only change the password");
+
+            wrefresh(win);
+
+            if focus == "ok_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 12, self.max_x/2 - 6 - "< OK >".len() as i32, "< OK >");
+            if focus == "ok_btn" {
+                attroff(attr);
+            }
+            if focus == "cancel_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 12, self.max_x/2 + 6, "< Cancel >");
+            if focus == "cancel_btn" {
+                attroff(attr);
+            }
+
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                exit = true;
+            } else if key == 9 /* TAB */ {
+                focus = match focus {
+                    "path" => "password",
+                    "password" => "new_password",
+                    "new_password" => "ok_btn",
+                    "ok_btn" => "cancel_btn",
+                    _ => "path",
+                }
+            } else if key == 10 /* ENTER */ {
+                match focus {
+                    "ok_btn" => {
+                        self.backup_account(manager.clone(), &path, &password, &new_password);
+                        exit = true;
+                    },
+                    "cancel_btn" => {
+                        exit = true;
+                    },
+                    _ => { }
+                }
+            } else if key == 263 /* BACKSPACE */ {
+                match focus {
+                    "path" => {
+                        path.pop();
+                    },
+                    "password" => {
+                        password.pop();
+                    },
+                    "new_password" => {
+                        new_password.pop();
+                    },
+                    _ => { }
+                }
+            } else {
+                let c = std::char::from_u32(key as u32).unwrap_or(' ');
+                match focus {
+                    "path" => path.push(c),
+                    "password" => password.push(c),
+                    "new_password" => new_password.push(c),
+                    _ => { }
+                }
+            }
+        }
+        self.current_mode = String::from("accounts");
+    }
+
+    /**
+     * Register a name for the current account on the name server
+     * @param self
+     * @param manager
+     */
+    fn draw_register_name_popup(&mut self, manager: Arc<Mutex<Manager>>) {
+        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 6);
+
+        let mut name = String::new();
+        let mut password = String::new();
+        let mut exit = false;
+        let mut focus = "name";
+
+        while !exit && !self.shutdown.is_requested() {
+            let win = newwin(12, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+
+            let title = "Register a name";
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, title);
+
+            let labels = ["Name:", "Password:"];
+            let start_label = start_x + 2;
+            let label_size = labels.iter().map(|l| l.len()).max().unwrap_or(0) as i32;
+            let start_edit_view = start_x + label_size + 6;
+            let width = self.max_x/2 - label_size - 12;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+
+            let masked_password: String = password.chars().map(|_| '*').collect();
+            let entries = [&name, &masked_password];
+            for (idx, label) in labels.iter().enumerate() {
+                let row = start_y + 4 + 2 * idx as i32;
+                mvprintw(row, start_label, label);
+                attron(attr);
+                let mut info_entry = entries[idx].clone();
+                for _ in 0..(width - entries[idx].len() as i32) {
+                    info_entry += " ";
+                }
+                mvprintw(row, start_edit_view, &info_entry);
+                attroff(attr);
+            }
+
+            wrefresh(win);
+
+            if focus == "ok_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 9, self.max_x/2 - 6 - "< OK >".len() as i32, "< OK >");
+            if focus == "ok_btn" {
+                attroff(attr);
+            }
+            if focus == "cancel_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 9, self.max_x/2 + 6, "< Cancel >");
+            if focus == "cancel_btn" {
+                attroff(attr);
+            }
+
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                exit = true;
+            } else if key == 9 /* TAB */ {
+                focus = match focus {
+                    "name" => "password",
+                    "password" => "ok_btn",
+                    "ok_btn" => "cancel_btn",
+                    _ => "name",
+                }
+            } else if key == 10 /* ENTER */ {
+                match focus {
+                    "ok_btn" => {
+                        let result = manager.lock().unwrap().register_name(&self.current_account, &password, &name);
+                        match self.report(result) {
+                            Some(true) => self.status = format!("Registering {}...", name),
+                            Some(false) => self.status = String::from("The daemon refused"),
+                            None => {},
+                        }
+                        exit = true;
+                    },
+                    "cancel_btn" => {
+                        exit = true;
+                    },
+                    _ => { }
+                }
+            } else if key == 263 /* BACKSPACE */ {
+                match focus {
+                    "name" => {
+                        name.pop();
+                    },
+                    "password" => {
+                        password.pop();
+                    },
+                    _ => { }
+                }
+            } else {
+                let c = std::char::from_u32(key as u32).unwrap_or(' ');
+                match focus {
+                    "name" => name.push(c),
+                    "password" => password.push(c),
+                    _ => { }
+                }
+            }
+        }
+        self.current_mode = String::from("accounts");
+    }
+
+    /**
+     * Change the password of the current account if a new one is given, then export it if a path is given
+     * @param self
+     * @param manager
+     * @param path where to write the archive, empty to skip the export
+     * @param password current password of the archive
+     * @param new_password empty to keep the current one
+     */
+    fn backup_account(&mut self, manager: Arc<Mutex<Manager>>, path: &str, password: &str, new_password: &str) {
+        let mut password = password;
+        if !new_password.is_empty() {
+            let result = manager.lock().unwrap().change_account_password(&self.current_account, password, new_password);
+            match self.report(result) {
+                Some(true) => {
+                    self.status = String::from("Password changed");
+                    password = new_password;
+                },
+                Some(false) => {
+                    self.status = String::from("Can't change the password: wrong password?");
+                    return;
+                },
+                None => return,
+            }
+        }
+        if !path.is_empty() {
+            let result = manager.lock().unwrap().export_to_file(&self.current_account, path, password);
+            match self.report(result) {
+                Some(true) => self.status = format!("Account exported to {}", path),
+                Some(false) => self.status = format!("Can't export the account to {}", path),
+                None => {},
+            }
+        }
+    }
+
+    fn draw_account_details_popup(&mut self, manager: Arc<Mutex<Manager>>) {
+        let details = manager.lock().unwrap().get_account_details(&self.current_account);
+        let mut details = match self.report(details) {
+            Some(details) => details,
+            None => {
+                self.current_mode = String::from("accounts");
+                return;
+            }
+        };
+        let editable = editable_details(&details.account_type);
+        let height = editable.len() as i32 + 8;
+        let (start_x, start_y) = (self.max_x/4, std::cmp::max(0, self.max_y/2 - height/2));
+        let mut focus = 0;
+        let mut exit = false;
+
+        while !exit && !self.shutdown.is_requested() {
+            let win = newwin(height, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+            wrefresh(win);
+
+            let title = "Edit account";
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, title);
+
+            let start_label = start_x + 2;
+            let label_size = editable.iter().map(|d| d.0.len()).max().unwrap_or(0) as i32;
+            let start_edit_view = start_x + label_size + 6;
+            let width = self.max_x/2 - label_size - 12;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            let mut row = start_y + 4;
+            for (idx, &(label, key, kind)) in editable.iter().enumerate() {
+                mvprintw(row, start_label, label);
+                let mut value = details.get(key);
+                if kind == DetailKind::Secret {
+                    value = value.chars().map(|_| '*').collect();
+                }
+                while (value.len() as i32) < width {
+                    value += " ";
+                }
+                if idx == focus {
+                    attron(attr);
+                }
+                mvprintw(row, start_edit_view, &value);
+                if idx == focus {
+                    attroff(attr);
+                }
+                row += 1;
+            }
+            mvprintw(row + 1, start_label, "ENTER: Save | ESC: Cancel | SPACE: Toggle");
+
+            let (_, key_name, kind) = editable[focus];
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                self.current_mode = String::from("accounts");
+                exit = true;
+            } else if key == 258 /* BOTTOM KEY */ {
+                focus = std::cmp::min(focus + 1, editable.len() - 1);
+            } else if key == 259 /* UP KEY */ {
+                focus = focus.saturating_sub(1);
+            } else if key == 10 /* ENTER */ {
+                let result = manager.lock().unwrap().set_account_details(&self.current_account, &details);
+                self.report(result);
+                self.current_mode = String::from("accounts");
+                exit = true;
+            } else if kind == DetailKind::Bool {
+                if key == ' ' as i32 {
+                    let toggled = if details.get(key_name) == "true" {"false"} else {"true"};
+                    let _ = details.set(key_name, toggled);
+                }
+            } else if key == 263 /* BACKSPACE */ {
+                let mut value = details.get(key_name);
+                value.pop();
+                if kind == DetailKind::Port && value.is_empty() {
+                    value = String::from("0");
+                }
+                let _ = details.set(key_name, &value);
+            } else {
+                let mut value = details.get(key_name);
+                if kind == DetailKind::Port && value == "0" {
+                    value = String::new();
+                }
+                value += &*std::char::from_u32(key as u32).unwrap_or(' ').to_string();
+                if let Err(e) = details.set(key_name, &value) {
+                    self.status = e;
+                }
+            }
+        }
+    }
+
+    fn draw_contacts_popup(&mut self, manager: Arc<Mutex<Manager>>, add: bool) {
+        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 5);
+
+        let mut entry = String::new();
+        let mut exit = false;
+        let mut focus = "entry";
+
+        while !exit && !self.shutdown.is_requested() {
+            let win = newwin(10, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+
+            let title = if add {"Add new contact"} else {"Send message"};
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, title);
+
+            let first_info = if add {"Id or username:"} else {"Message:"};
+            let start_label = start_x + 2;
+            let label_size = first_info.len() as i32;
+            let start_edit_view = start_x + label_size + 6;
+
+            mvprintw(start_y + 4, start_label,first_info);
             let width = self.max_x/2 - label_size - 12;
             let attr = COLOR_PAIR(COLOR_SELECTED);
             attron(attr);
@@ -452,7 +1802,7 @@ impl UserInterface {
             for _ in 0..(width - entry.len() as i32) {
                 info_entry += " ";
             }
-            mvprintw(start_y + 4, start_edit_view, &*info_entry);
+            mvprintw(start_y + 4, start_edit_view, &info_entry);
             attroff(attr);
 
             wrefresh(win);
@@ -491,11 +1841,20 @@ impl UserInterface {
             } else if key == 10 /* ENTER */ {
                 match focus {
                     "ok_btn" => {
-                        if add {
-                            manager.lock().unwrap().add_contact(&*self.current_account, &*entry);
+                        let result = if add {
+                            // Usernames are accepted too, added once the name server answered
+                            let m = manager.lock().unwrap();
+                            m.resolve_name(&self.current_account, &entry).and_then(|contact| match contact {
+                                Some(contact) => m.add_contact(&self.current_account, &contact),
+                                None => {
+                                    self.pending_contact = entry.clone();
+                                    Ok(())
+                                },
+                            })
                         } else {
-                            manager.lock().unwrap().send_interaction(&*self.current_account, &*self.current_contact, &*entry);
-                        }
+                            manager.lock().unwrap().send_interaction(&self.current_account, &self.current_contact, &entry).map(|_| ())
+                        };
+                        self.report(result);
                         self.current_mode = String::from("contacts");
                         exit = true;
                     },
@@ -506,11 +1865,8 @@ impl UserInterface {
                     _ => { }
                 }
             } else if key == 263 /* BACKSPACE */ {
-                match focus {
-                    "entry" => {
-                        entry.pop();
-                    },
-                    _ => { }
+                if focus == "entry" {
+                    entry.pop();
                 }
             } else {
                 match focus {
@@ -527,26 +1883,423 @@ impl UserInterface {
         }
     }
 
+    /**
+     * Send tones in the selected call if it is in progress,
+     * else call the number or uri typed
+     * @param self
+     * @param manager
+     */
+    fn draw_dial_pad(&mut self, manager: Arc<Mutex<Manager>>) {
+        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 8);
+        let mut entry = String::new();
+        let mut exit = false;
+
+        timeout(1000);
+        while !exit && !self.shutdown.is_requested() {
+            let call = manager.lock().unwrap().call(&self.current_call).cloned()
+                .filter(|call| call.state == CallState::Current);
+            let win = newwin(16, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+            wrefresh(win);
+
+            let title = match call {
+                Some(ref call) => format!("Dial pad - {}", self.contact_label(&manager, &call.account_id, &call.peer)),
+                None => String::from("Dial pad"),
+            };
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, &title);
+            let label = if call.is_some() {"Tones sent:"} else {"Number or uri:"};
+            mvprintw(start_y + 4, start_x + 2, label);
+            let start_edit_view = start_x + label.len() as i32 + 4;
+            let width = self.max_x/2 - label.len() as i32 - 8;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            attron(attr);
+            let mut info_entry = entry.clone();
+            for _ in 0..(width - entry.len() as i32) {
+                info_entry += " ";
+            }
+            mvprintw(start_y + 4, start_edit_view, &info_entry);
+            attroff(attr);
+            for (row, keys) in ["1 2 3", "4 5 6", "7 8 9", "* 0 #"].iter().enumerate() {
+                mvprintw(start_y + 6 + row as i32, self.max_x/2 - 2, keys);
+            }
+            let help = if call.is_some() {"ESC: return"} else {"Enter: Call | ESC: return"};
+            mvprintw(start_y + 13, self.max_x/2 - help.len() as i32/2, help);
+
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                exit = true;
+            } else if key == 263 /* BACKSPACE */ {
+                if call.is_none() {
+                    entry.pop();
+                }
+            } else if key == 10 /* ENTER */ {
+                if call.is_none() && !entry.is_empty() {
+                    let result = manager.lock().unwrap().place_call(&self.current_account, &entry);
+                    if let Some(call_id) = self.report(result) {
+                        self.current_call = call_id;
+                        exit = true;
+                    }
+                }
+            } else if let Some(c) = std::char::from_u32(key as u32) {
+                match call {
+                    Some(ref call) => {
+                        if is_dtmf(c) {
+                            let result = manager.lock().unwrap().play_dtmf(&call.id, &c.to_string());
+                            if self.report(result).is_some() {
+                                entry.push(c);
+                            }
+                        }
+                    },
+                    None => entry.push(c),
+                }
+            }
+        }
+        // Stay with the call in progress, if any
+        if manager.lock().unwrap().call(&self.current_call).is_some() {
+            self.current_mode = String::from("call");
+        } else {
+            self.current_mode = self.previous_mode.clone();
+        }
+    }
+
+    /**
+     * Ask the path of the file to send to the current contact,
+     * or where to save the selected incoming file
+     * @param self
+     * @param manager
+     * @param send true to send a file, false to accept one
+     */
+    fn draw_path_popup(&mut self, manager: Arc<Mutex<Manager>>, send: bool) {
+        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 5);
+        let return_mode = if send {"contacts"} else {"transfers"};
+
+        let mut entry = String::new();
+        if !send {
+            // Suggest to save in the home directory
+            let display_name = manager.lock().unwrap().data_transfer(self.current_transfer)
+                .map(|t| t.display_name.clone()).unwrap_or_default();
+            let home = std::env::var("HOME").unwrap_or(String::from("."));
+            entry = format!("{}/{}", home, display_name);
+        }
+        let mut exit = false;
+        let mut focus = "entry";
+
+        while !exit && !self.shutdown.is_requested() {
+            let win = newwin(10, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+
+            let title = if send {"Send file"} else {"Accept file"};
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, title);
+
+            let first_info = if send {"File to send:"} else {"Save as:"};
+            let start_label = start_x + 2;
+            let label_size = first_info.len() as i32;
+            let start_edit_view = start_x + label_size + 6;
+
+            mvprintw(start_y + 4, start_label, first_info);
+            let width = self.max_x/2 - label_size - 12;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            attron(attr);
+            let mut info_entry = entry.clone();
+            for _ in 0..(width - entry.len() as i32) {
+                info_entry += " ";
+            }
+            mvprintw(start_y + 4, start_edit_view, &info_entry);
+            attroff(attr);
+
+            wrefresh(win);
+
+            if focus == "ok_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 7, self.max_x/2 - 6 - "< OK >".len() as i32, "< OK >");
+            if focus == "ok_btn" {
+                attroff(attr);
+            }
+            if focus == "cancel_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 7, self.max_x/2 + 6, "< Cancel >");
+            if focus == "cancel_btn" {
+                attroff(attr);
+            }
+
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                exit = true;
+            } else if key == 9 /* TAB */ {
+                focus = match focus {
+                    "entry" => "ok_btn",
+                    "ok_btn" => "cancel_btn",
+                    _ => "entry",
+                }
+            } else if key == 10 /* ENTER */ {
+                match focus {
+                    "ok_btn" => {
+                        if send {
+                            let result = manager.lock().unwrap().send_file(&self.current_account, &self.current_contact, &entry);
+                            if let Some(id) = self.report(result) {
+                                self.current_transfer = id;
+                                self.status = format!("Sending {}, press T to follow it", entry);
+                            }
+                        } else {
+                            let result = manager.lock().unwrap().accept_transfer(self.current_transfer, &entry);
+                            self.report(result);
+                        }
+                        exit = true;
+                    },
+                    "cancel_btn" => {
+                        exit = true;
+                    },
+                    _ => { }
+                }
+            } else if key == 263 /* BACKSPACE */ {
+                if focus == "entry" {
+                    entry.pop();
+                }
+            } else if focus == "entry" {
+                entry += &*std::char::from_u32(key as u32).unwrap_or(' ').to_string();
+            }
+        }
+        self.current_mode = String::from(return_mode);
+    }
+
+    /**
+     * Ask where to transfer the selected call, or who to call in a new conference
+     * @param self
+     * @param manager
+     * @param transfer true to transfer the selected call, false to create a conference
+     */
+    fn draw_call_popup(&mut self, manager: Arc<Mutex<Manager>>, transfer: bool) {
+        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 5);
+
+        let mut entry = String::new();
+        let mut exit = false;
+        let mut focus = "entry";
+
+        while !exit && !self.shutdown.is_requested() {
+            let win = newwin(10, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+
+            let title = if transfer {"Transfer call"} else {"New conference"};
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, title);
+
+            let first_info = if transfer {"Transfer to:"} else {"Participants (comma separated):"};
+            let start_label = start_x + 2;
+            let label_size = first_info.len() as i32;
+            let start_edit_view = start_x + label_size + 6;
+
+            mvprintw(start_y + 4, start_label, first_info);
+            let width = self.max_x/2 - label_size - 12;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            attron(attr);
+            let mut info_entry = entry.clone();
+            for _ in 0..(width - entry.len() as i32) {
+                info_entry += " ";
+            }
+            mvprintw(start_y + 4, start_edit_view, &info_entry);
+            attroff(attr);
+
+            wrefresh(win);
+
+            if focus == "ok_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 7, self.max_x/2 - 6 - "< OK >".len() as i32, "< OK >");
+            if focus == "ok_btn" {
+                attroff(attr);
+            }
+            if focus == "cancel_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 7, self.max_x/2 + 6, "< Cancel >");
+            if focus == "cancel_btn" {
+                attroff(attr);
+            }
+
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                exit = true;
+            } else if key == 9 /* TAB */ {
+                focus = match focus {
+                    "entry" => "ok_btn",
+                    "ok_btn" => "cancel_btn",
+                    _ => "entry",
+                }
+            } else if key == 10 /* ENTER */ {
+                match focus {
+                    "ok_btn" => {
+                        if transfer {
+                            let result = manager.lock().unwrap().transfer(&self.current_call, &entry);
+                            if self.report(result).is_some() {
+                                self.status = format!("Call transferred to {}", entry);
+                            }
+                        } else {
+                            let participants: Vec<String> = entry.split(',').map(|p| p.trim().to_string())
+                                                                 .filter(|p| !p.is_empty()).collect();
+                            let result = manager.lock().unwrap().create_conference(&self.current_account, &participants);
+                            self.report(result);
+                        }
+                        exit = true;
+                    },
+                    "cancel_btn" => {
+                        exit = true;
+                    },
+                    _ => { }
+                }
+            } else if key == 263 /* BACKSPACE */ {
+                if focus == "entry" {
+                    entry.pop();
+                }
+            } else if focus == "entry" {
+                entry += &*std::char::from_u32(key as u32).unwrap_or(' ').to_string();
+            }
+        }
+        self.current_mode = String::from("call");
+    }
+
+    /**
+     * Ask the account password to revoke the selected device, or to link a new one
+     * @param self
+     * @param manager
+     * @param revoke true to revoke the selected device, false to get a PIN
+     */
+    fn draw_password_popup(&mut self, manager: Arc<Mutex<Manager>>, revoke: bool) {
+        let (start_x, start_y) = (self.max_x/4, self.max_y/2 - 5);
+
+        let mut password = String::new();
+        let mut exit = false;
+        let mut focus = "entry";
+
+        while !exit && !self.shutdown.is_requested() {
+            let win = newwin(10, self.max_x/2, start_y, start_x);
+            box_(win, 0, 0);
+
+            let title = if revoke {format!("Revoke {}", self.current_device)} else {String::from("Link a new device")};
+            mvprintw(start_y + 2, self.max_x/2 - title.len() as i32/2, &title);
+
+            let first_info = "Password:";
+            let start_label = start_x + 2;
+            let label_size = first_info.len() as i32;
+            let start_edit_view = start_x + label_size + 6;
+
+            mvprintw(start_y + 4, start_label, first_info);
+            let width = self.max_x/2 - label_size - 12;
+            let attr = COLOR_PAIR(COLOR_SELECTED);
+            attron(attr);
+            let mut info_entry: String = password.chars().map(|_| '*').collect();
+            for _ in 0..(width - password.len() as i32) {
+                info_entry += " ";
+            }
+            mvprintw(start_y + 4, start_edit_view, &info_entry);
+            attroff(attr);
+
+            wrefresh(win);
+
+            if focus == "ok_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 7, self.max_x/2 - 6 - "< OK >".len() as i32, "< OK >");
+            if focus == "ok_btn" {
+                attroff(attr);
+            }
+            if focus == "cancel_btn" {
+                attron(attr);
+            }
+            mvprintw(start_y + 7, self.max_x/2 + 6, "< Cancel >");
+            if focus == "cancel_btn" {
+                attroff(attr);
+            }
+
+            let key = getch();
+            if key == -1 /* ERR */ {}
+            else if key == 27 /* ESC */ {
+                exit = true;
+            } else if key == 9 /* TAB */ {
+                focus = match focus {
+                    "entry" => "ok_btn",
+                    "ok_btn" => "cancel_btn",
+                    _ => "entry",
+                }
+            } else if key == 10 /* ENTER */ {
+                match focus {
+                    "ok_btn" => {
+                        let result = if revoke {
+                            manager.lock().unwrap().revoke_device(&self.current_account, &password, &self.current_device)
+                        } else {
+                            manager.lock().unwrap().export_on_ring(&self.current_account, &password)
+                        };
+                        match self.report(result) {
+                            Some(true) => self.status = String::from(if revoke {"Revoking the device..."} else {"Generating a PIN..."}),
+                            Some(false) => self.status = String::from("The daemon refused"),
+                            None => {},
+                        }
+                        exit = true;
+                    },
+                    "cancel_btn" => {
+                        exit = true;
+                    },
+                    _ => { }
+                }
+            } else if key == 263 /* BACKSPACE */ {
+                if focus == "entry" {
+                    password.pop();
+                }
+            } else if focus == "entry" {
+                password += &*std::char::from_u32(key as u32).unwrap_or(' ').to_string();
+            }
+        }
+        self.current_mode = String::from("devices");
+    }
+
     fn draw_interactions(&mut self, manager: Arc<Mutex<Manager>>) {
         if self.current_mode == "contacts" {
             let mut row = 3;
             // Linked interactions
-            let interactions = manager.lock().unwrap().messages.clone();
-            let mut interactions: Vec<(String, Interaction)> = interactions.into_iter()
-               .filter(|tup| tup.0 == self.current_account && tup.1.author_ring_id == self.current_contact)
-               .collect();
+            let mut interactions: Vec<Interaction> = {
+                let mut manager = manager.lock().unwrap();
+                // Showing the conversation reads it
+                manager.mark_read(&self.current_account, &self.current_contact);
+                manager.conversation(&self.current_account, &self.current_contact)
+                       .map(|c| c.interactions.clone()).unwrap_or_default()
+            };
             interactions.reverse();
 
-            if interactions.len() != 0 {
-                for (_, interaction) in interactions {
+            if !interactions.is_empty() {
+                for interaction in interactions.into_iter().skip(self.interactions_offset) {
                     if row == self.max_y {
                         return;
                     }
-                    let interaction_str = format!("{}: {}", interaction.time.rfc3339(), interaction.body);
-                    mvprintw(row, 2*self.max_x/3 + 4, &*interaction_str);
+                    let ticks = match interaction.direction {
+                        Direction::Incoming => "",
+                        Direction::Outgoing => match interaction.status {
+                            MessageStatus::Sending => " .",
+                            MessageStatus::Sent => " v",
+                            MessageStatus::Delivered => " vv",
+                            MessageStatus::Read => " vv read",
+                            MessageStatus::Failure => " ! not sent",
+                        },
+                    };
+                    let interaction_str = format!("{}: {}{}", interaction.time.rfc3339(), interaction.summary(), ticks);
+                    mvprintw(row, 2*self.max_x/3 + 4, &interaction_str);
                     row += 1;
                 }
             }
         }
     }
 }
+
+/**
+ * @param manager
+ * @param account_id
+ * @return the alias of the account, or its id if unknown
+ */
+fn account_alias(manager: &Arc<Mutex<Manager>>, account_id: &str) -> String {
+    manager.lock().unwrap().accounts.iter()
+        .find(|account| account.id == account_id)
+        .map(|account| account.alias.clone())
+        .unwrap_or_else(|| account_id.to_string())
+}